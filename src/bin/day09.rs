@@ -1,12 +1,30 @@
 use std::collections::HashMap;
 
+use advent_of_code_2015::rng::Rng;
+
+/// Largest number of cities for which the heuristic also runs the exact search.
+const EXACT_SEARCH_LIMIT: usize = 9;
+
 struct Distances<'a> {
     cities: Vec<&'a str>,
     distances: HashMap<(&'a str, &'a str), usize>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+struct HeuristicRoute<'a> {
+    route: Vec<&'a str>,
+    length: usize,
+    /// How much longer than the optimum the route is (0.1 means 10%), if the optimum is known.
+    gap: Option<f64>,
+}
+
 impl<'a> Distances<'a> {
     fn from_str(s: &'a str) -> Result<Self, ()> {
+        Self::parse(s, false)
+    }
+
+    /// With `directed` set "A to B" only allows travelling from A to B.
+    fn parse(s: &'a str, directed: bool) -> Result<Self, ()> {
         let mut cities = Vec::new();
         let mut distances = HashMap::new();
         for line in s.lines() {
@@ -20,39 +38,269 @@ impl<'a> Distances<'a> {
                 cities.push(city2);
             }
             distances.insert((city1, city2), distance);
-            distances.insert((city2, city1), distance);
+            if !directed {
+                distances.insert((city2, city1), distance);
+            }
         }
         Ok(Self { cities, distances })
     }
 
-    fn path_length(&self, path: &[&'a str]) -> usize {
+    /// Returns `None` if any leg of the path has no distance specified.
+    fn path_length(&self, path: &[&'a str]) -> Option<usize> {
         path.windows(2)
-            .map(|pair| {
-                self.distances
-                    .get(&(pair[0], pair[1]))
-                    .expect("all distances should be specified")
-            })
+            .map(|pair| self.distances.get(&(pair[0], pair[1])).cloned())
             .sum()
     }
 
-    fn find_shortest_and_longest_path(&self) -> (usize, usize) {
-        let all_cities = self.cities.clone();
-        let mut best_length = self.path_length(&all_cities);
-        let mut worst_length = best_length;
+    /// Returns `None` if there's no path visiting all the cities.
+    fn find_shortest_and_longest_path(&self) -> Option<(usize, usize)> {
+        self.cities
+            .iter()
+            .map(|start| self.find_shortest_and_longest_path_from(start))
+            .fold(None, merge_lengths)
+    }
+
+    /// Like `find_shortest_and_longest_path` but starting in `start`.
+    fn find_shortest_and_longest_path_from(&self, start: &str) -> Option<(usize, usize)> {
+        if self.cities == [start] {
+            return Some((0, 0));
+        }
+        self.cities
+            .iter()
+            .filter(|end| **end != start)
+            .map(|end| self.find_shortest_and_longest_path_between(start, end))
+            .fold(None, merge_lengths)
+    }
+
+    /// Shortest and longest routes visiting all the cities and coming back to the first one.
+    fn find_shortest_and_longest_round_trip(&self) -> Option<(usize, usize)> {
+        let start = self.cities.first()?;
+        self.find_shortest_and_longest_path_between(start, start)
+    }
+
+    /// Like `find_shortest_and_longest_path` but starting in `start` and ending in `end`.
+    ///
+    /// If `start` and `end` are the same city the route is a round trip.
+    fn find_shortest_and_longest_path_between(
+        &self,
+        start: &str,
+        end: &str,
+    ) -> Option<(usize, usize)> {
+        let start = *self.cities.iter().find(|city| **city == start)?;
+        let end = *self.cities.iter().find(|city| **city == end)?;
+        let middle = self
+            .cities
+            .iter()
+            .cloned()
+            .filter(|city| *city != start && *city != end)
+            .collect::<Vec<_>>();
 
-        permutations(all_cities, |candidate| {
-            let new_length = self.path_length(candidate);
-            if new_length < best_length {
-                best_length = new_length;
-            } else if worst_length < new_length {
-                worst_length = new_length;
+        let mut result = None;
+        let mut check = |middle: &Vec<&'a str>| {
+            let mut path = Vec::with_capacity(middle.len() + 2);
+            path.push(start);
+            path.extend(middle);
+            path.push(end);
+            result = merge_lengths(result, self.path_length(&path).map(|l| (l, l)));
+        };
+        if middle.is_empty() {
+            check(&middle);
+        } else {
+            permutations(middle, check);
+        }
+        result
+    }
+
+    /// Looks for a short route with nearest neighbour construction improved by 2-opt and Or-opt
+    /// moves, restarting from random cities `restarts` times.
+    ///
+    /// Returns `None` if the best route found uses a missing distance.
+    fn find_short_route_heuristic(
+        &self,
+        round_trip: bool,
+        restarts: usize,
+        seed: u64,
+    ) -> Option<HeuristicRoute<'a>> {
+        if self.cities.is_empty() {
+            return None;
+        }
+        let costs = self.cost_matrix(!round_trip);
+        let mut rng = Rng::new(seed);
+        let mut best: Option<(i64, Vec<usize>)> = None;
+        for _ in 0..restarts.max(1) {
+            let mut tour =
+                nearest_neighbour_tour(&costs, self.cities.len(), rng.below(self.cities.len()));
+            while two_opt(&costs, &mut tour) || or_opt(&costs, &mut tour) {}
+            let cost = tour_cost(&costs, &tour);
+            if best
+                .as_ref()
+                .map(|(best_cost, _)| cost < *best_cost)
+                .unwrap_or(true)
+            {
+                best = Some((cost, tour));
+            }
+        }
+        let (_, mut tour) = best?;
+
+        if !round_trip {
+            // the extra city at the end of the tour only connects the ends of the path
+            let dummy = tour.iter().position(|idx| *idx == self.cities.len())?;
+            tour.rotate_left(dummy + 1);
+            tour.pop();
+        }
+        let mut route = tour
+            .into_iter()
+            .map(|idx| self.cities[idx])
+            .collect::<Vec<_>>();
+        if round_trip {
+            route.push(route[0]);
+        }
+        let length = self.path_length(&route)?;
+
+        let optimum = if self.cities.len() <= EXACT_SEARCH_LIMIT {
+            if round_trip {
+                self.find_shortest_and_longest_round_trip()
+            } else {
+                self.find_shortest_and_longest_path()
+            }
+            .map(|(shortest, _)| shortest)
+        } else {
+            None
+        };
+        let gap = optimum.map(|optimum| {
+            if optimum == 0 {
+                0.0
+            } else {
+                (length - optimum) as f64 / optimum as f64
             }
         });
 
-        (best_length, worst_length)
+        Some(HeuristicRoute { route, length, gap })
+    }
+
+    /// Distances between cities by index with missing ones replaced by a huge penalty.
+    ///
+    /// With `open_path` set there's an extra city (last index) that is at zero distance from
+    /// all the others so that a tour through it corresponds to an open path.
+    fn cost_matrix(&self, open_path: bool) -> Vec<Vec<i64>> {
+        let size = self.cities.len() + usize::from(open_path);
+        let missing = i64::MAX / (4 * size as i64);
+        (0..size)
+            .map(|from| {
+                (0..size)
+                    .map(|to| {
+                        if from == self.cities.len() || to == self.cities.len() {
+                            0
+                        } else {
+                            self.distances
+                                .get(&(self.cities[from], self.cities[to]))
+                                .map(|d| *d as i64)
+                                .unwrap_or(missing)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn merge_lengths(a: Option<(usize, usize)>, b: Option<(usize, usize)>) -> Option<(usize, usize)> {
+    match (a, b) {
+        (Some((best1, worst1)), Some((best2, worst2))) => {
+            Some((best1.min(best2), worst1.max(worst2)))
+        }
+        (a, b) => a.or(b),
     }
 }
 
+fn tour_cost(costs: &[Vec<i64>], tour: &[usize]) -> i64 {
+    (0..tour.len())
+        .map(|i| costs[tour[i]][tour[(i + 1) % tour.len()]])
+        .sum()
+}
+
+/// Visits the `cities` first cities greedily from `start` and then appends any remaining ones.
+fn nearest_neighbour_tour(costs: &[Vec<i64>], cities: usize, start: usize) -> Vec<usize> {
+    let mut visited = vec![false; costs.len()];
+    let mut tour = Vec::with_capacity(costs.len());
+    let mut current = start;
+    visited[current] = true;
+    tour.push(current);
+    while tour.len() < cities {
+        current = (0..cities)
+            .filter(|idx| !visited[*idx])
+            .min_by_key(|idx| costs[current][*idx])
+            .expect("there should be an unvisited city");
+        visited[current] = true;
+        tour.push(current);
+    }
+    tour.extend(cities..costs.len());
+    tour
+}
+
+/// Reverses segments of the tour while that makes it shorter. Returns whether anything changed.
+fn two_opt(costs: &[Vec<i64>], tour: &mut [usize]) -> bool {
+    let n = tour.len();
+    let mut improved = false;
+    for i in 1..n {
+        // cost of tour[i..=j] travelled forwards and backwards
+        let mut forward = 0;
+        let mut backward = 0;
+        for j in i + 1..n {
+            forward += costs[tour[j - 1]][tour[j]];
+            backward += costs[tour[j]][tour[j - 1]];
+            let (before, after) = (tour[i - 1], tour[(j + 1) % n]);
+            let old = costs[before][tour[i]] + forward + costs[tour[j]][after];
+            let new = costs[before][tour[j]] + backward + costs[tour[i]][after];
+            if new < old {
+                tour[i..=j].reverse();
+                improved = true;
+                forward = 0;
+                backward = 0;
+                for k in i + 1..=j {
+                    forward += costs[tour[k - 1]][tour[k]];
+                    backward += costs[tour[k]][tour[k - 1]];
+                }
+            }
+        }
+    }
+    improved
+}
+
+/// Moves runs of up to 3 cities elsewhere in the tour while that makes it shorter. Returns
+/// whether anything changed.
+fn or_opt(costs: &[Vec<i64>], tour: &mut Vec<usize>) -> bool {
+    let n = tour.len();
+    let mut improved = false;
+    for len in 1..=3 {
+        let mut start = 1;
+        while start + len <= n {
+            let end = start + len - 1;
+            let (before, after) = (tour[start - 1], tour[(end + 1) % n]);
+            let removal_gain =
+                costs[before][tour[start]] + costs[tour[end]][after] - costs[before][after];
+            let target = (0..n)
+                .filter(|k| *k + 1 < start || *k > end)
+                .map(|k| {
+                    let (a, b) = (tour[k], tour[(k + 1) % n]);
+                    let insertion_cost = costs[a][tour[start]] + costs[tour[end]][b] - costs[a][b];
+                    (insertion_cost, k)
+                })
+                .filter(|(insertion_cost, _)| *insertion_cost < removal_gain)
+                .min();
+            if let Some((_, k)) = target {
+                let segment = tour.drain(start..=end).collect::<Vec<_>>();
+                let insert_at = if k > end { k + 1 - len } else { k + 1 };
+                tour.splice(insert_at..insert_at, segment);
+                improved = true;
+            } else {
+                start += 1;
+            }
+        }
+    }
+    improved
+}
+
 fn permutations<T, F>(elements: Vec<T>, callback: F) -> Vec<T>
 where
     F: FnMut(&Vec<T>),
@@ -69,7 +317,7 @@ where
     } else {
         (elements, callback) = gen_permutations(k - 1, elements, callback);
         for i in 0..k - 1 {
-            elements.swap(if k % 2 == 0 { i } else { 0 }, k - 1);
+            elements.swap(if k.is_multiple_of(2) { i } else { 0 }, k - 1);
             (elements, callback) = gen_permutations(k - 1, elements, callback);
         }
     }
@@ -79,9 +327,25 @@ where
 fn main() {
     let distances = Distances::<'_>::from_str(include_str!("../../inputs/day09.txt"))
         .expect("input should parse correctly");
-    let (shortest, longest) = distances.find_shortest_and_longest_path();
+    let (shortest, longest) = distances
+        .find_shortest_and_longest_path()
+        .expect("there should be a path visiting all cities");
     println!("Part 1: {shortest}");
     println!("Part 2: {longest}");
+    if let Some((shortest, longest)) = distances.find_shortest_and_longest_round_trip() {
+        println!("Round trip: {shortest} (shortest), {longest} (longest)");
+    }
+    if let Some(heuristic) = distances.find_short_route_heuristic(false, 10, 2015) {
+        let gap = heuristic
+            .gap
+            .map(|gap| format!(" ({:.1}% over the optimum)", gap * 100.0))
+            .unwrap_or_default();
+        println!(
+            "Heuristic: {}{gap} via {}",
+            heuristic.length,
+            heuristic.route.join(" -> ")
+        );
+    }
 }
 
 #[cfg(test)]
@@ -93,14 +357,168 @@ mod tests {
     #[test]
     fn test_sample_path_length() {
         let distances = Distances::<'_>::from_str(SAMPLE).unwrap();
-        assert_eq!(distances.path_length(&["London", "Dublin", "Belfast"]), 605);
-        assert_eq!(distances.path_length(&["London", "Belfast", "Dublin"]), 659);
+        assert_eq!(
+            distances.path_length(&["London", "Dublin", "Belfast"]),
+            Some(605)
+        );
+        assert_eq!(
+            distances.path_length(&["London", "Belfast", "Dublin"]),
+            Some(659)
+        );
     }
 
     #[test]
     fn test_sample() {
         let distances = Distances::<'_>::from_str(SAMPLE).unwrap();
-        assert_eq!(distances.find_shortest_and_longest_path(), (605, 982));
+        assert_eq!(distances.find_shortest_and_longest_path(), Some((605, 982)));
+    }
+
+    #[test]
+    fn test_directed() {
+        let distances = Distances::<'_>::parse(SAMPLE, true).unwrap();
+        assert_eq!(
+            distances.path_length(&["London", "Dublin", "Belfast"]),
+            Some(605)
+        );
+        assert_eq!(
+            distances.path_length(&["Belfast", "Dublin", "London"]),
+            None
+        );
+        assert_eq!(distances.find_shortest_and_longest_path(), Some((605, 605)));
+
+        let distances =
+            Distances::<'_>::parse("A to B = 1\nB to C = 2\nC to A = 3\nA to C = 10", true)
+                .unwrap();
+        assert_eq!(distances.find_shortest_and_longest_path(), Some((3, 5)));
+    }
+
+    #[test]
+    fn test_missing_distances() {
+        let distances = Distances::<'_>::from_str("A to B = 1\nB to C = 2\nC to D = 3").unwrap();
+        assert_eq!(distances.path_length(&["A", "C"]), None);
+        assert_eq!(distances.find_shortest_and_longest_path(), Some((6, 6)));
+
+        let distances = Distances::<'_>::from_str("A to B = 1\nA to C = 2\nA to D = 3").unwrap();
+        assert_eq!(distances.find_shortest_and_longest_path(), None);
+    }
+
+    #[test]
+    fn test_sample_fixed_cities() {
+        let distances = Distances::<'_>::from_str(SAMPLE).unwrap();
+        assert_eq!(
+            distances.find_shortest_and_longest_path_from("London"),
+            Some((605, 659))
+        );
+        assert_eq!(
+            distances.find_shortest_and_longest_path_from("Dublin"),
+            Some((659, 982))
+        );
+        assert_eq!(
+            distances.find_shortest_and_longest_path_between("London", "Belfast"),
+            Some((605, 605))
+        );
+        assert_eq!(
+            distances.find_shortest_and_longest_path_between("Dublin", "Belfast"),
+            Some((982, 982))
+        );
+        assert_eq!(
+            distances.find_shortest_and_longest_path_between("London", "Paris"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sample_round_trip() {
+        let distances = Distances::<'_>::from_str(SAMPLE).unwrap();
+        assert_eq!(
+            distances.find_shortest_and_longest_round_trip(),
+            Some((1123, 1123))
+        );
+        assert_eq!(
+            distances.find_shortest_and_longest_path_between("Dublin", "Dublin"),
+            Some((1123, 1123))
+        );
+
+        let distances =
+            Distances::<'_>::parse("A to B = 1\nB to C = 2\nC to A = 3\nA to C = 10", true)
+                .unwrap();
+        assert_eq!(
+            distances.find_shortest_and_longest_round_trip(),
+            Some((6, 6))
+        );
+
+        let distances = Distances::<'_>::from_str("A to B = 1\nB to C = 2\nC to D = 3").unwrap();
+        assert_eq!(distances.find_shortest_and_longest_round_trip(), None);
+    }
+
+    #[test]
+    fn test_sample_heuristic() {
+        let distances = Distances::<'_>::from_str(SAMPLE).unwrap();
+        let heuristic = distances.find_short_route_heuristic(false, 3, 1).unwrap();
+        assert_eq!(heuristic.length, 605);
+        assert_eq!(distances.path_length(&heuristic.route), Some(605));
+        assert_eq!(heuristic.gap, Some(0.0));
+
+        let heuristic = distances.find_short_route_heuristic(true, 3, 1).unwrap();
+        assert_eq!(heuristic.length, 1123);
+        assert_eq!(heuristic.route.len(), 4);
+        assert_eq!(heuristic.route.first(), heuristic.route.last());
+    }
+
+    fn random_input(cities: usize, seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let mut input = String::new();
+        for i in 0..cities {
+            for j in i + 1..cities {
+                input += &format!("C{i} to C{j} = {}\n", 1 + rng.below(100));
+            }
+        }
+        input
+    }
+
+    #[test]
+    fn test_heuristic_against_exact() {
+        for seed in 0..5 {
+            let input = random_input(8, seed);
+            let distances = Distances::<'_>::from_str(&input).unwrap();
+            let (shortest, _) = distances.find_shortest_and_longest_path().unwrap();
+            let heuristic = distances
+                .find_short_route_heuristic(false, 8, seed)
+                .unwrap();
+            assert_eq!(heuristic.route.len(), 8);
+            assert_eq!(
+                distances.path_length(&heuristic.route),
+                Some(heuristic.length)
+            );
+            assert!(heuristic.length >= shortest);
+            let gap = heuristic.gap.unwrap();
+            assert!((gap - (heuristic.length - shortest) as f64 / shortest as f64).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_heuristic_many_cities() {
+        // cities on a line, visiting them in order is optimal
+        let cities = 150;
+        let mut input = String::new();
+        for i in 0..cities {
+            for j in i + 1..cities {
+                input += &format!("C{i} to C{j} = {}\n", j - i);
+            }
+        }
+        let distances = Distances::<'_>::from_str(&input).unwrap();
+        let heuristic = distances.find_short_route_heuristic(false, 2, 7).unwrap();
+        assert_eq!(heuristic.length, cities - 1);
+        assert_eq!(heuristic.gap, None);
+
+        let heuristic = distances.find_short_route_heuristic(true, 2, 7).unwrap();
+        assert_eq!(heuristic.length, 2 * (cities - 1));
+    }
+
+    #[test]
+    fn test_heuristic_infeasible() {
+        let distances = Distances::<'_>::from_str("A to B = 1\nA to C = 2\nA to D = 3").unwrap();
+        assert_eq!(distances.find_short_route_heuristic(false, 4, 0), None);
     }
 
     #[test]
@@ -1,102 +1,391 @@
-use std::mem;
+use std::collections::{HashMap, HashSet};
+
+use advent_of_code_2015::rle::AddUniq;
 
 const INPUT: &str = "1321131112";
 
-struct Uniq<I, T> {
-    iter: I,
-    next: Option<T>,
+/// Conway's audioactive elements: name, digits and what the element decays into (dot separated).
+const ELEMENTS: [(&str, &str, &str); 92] = [
+    ("H", "22", "H"),
+    ("He", "13112221133211322112211213322112", "Hf.Pa.H.Ca.Li"),
+    ("Li", "312211322212221121123222112", "He"),
+    (
+        "Be",
+        "111312211312113221133211322112211213322112",
+        "Ge.Ca.Li",
+    ),
+    ("B", "1321132122211322212221121123222112", "Be"),
+    ("C", "3113112211322112211213322112", "B"),
+    ("N", "111312212221121123222112", "C"),
+    ("O", "132112211213322112", "N"),
+    ("F", "31121123222112", "O"),
+    ("Ne", "111213322112", "F"),
+    ("Na", "123222112", "Ne"),
+    ("Mg", "3113322112", "Pm.Na"),
+    ("Al", "1113222112", "Mg"),
+    ("Si", "1322112", "Al"),
+    ("P", "311311222112", "Ho.Si"),
+    ("S", "1113122112", "P"),
+    ("Cl", "132112", "S"),
+    ("Ar", "3112", "Cl"),
+    ("K", "1112", "Ar"),
+    ("Ca", "12", "K"),
+    ("Sc", "3113112221133112", "Ho.Pa.H.Ca.Co"),
+    ("Ti", "11131221131112", "Sc"),
+    ("V", "13211312", "Ti"),
+    ("Cr", "31132", "V"),
+    ("Mn", "111311222112", "Cr.Si"),
+    ("Fe", "13122112", "Mn"),
+    ("Co", "32112", "Fe"),
+    ("Ni", "11133112", "Zn.Co"),
+    ("Cu", "131112", "Ni"),
+    ("Zn", "312", "Cu"),
+    ("Ga", "13221133122211332", "Eu.Ca.Ac.H.Ca.Zn"),
+    ("Ge", "31131122211311122113222", "Ho.Ga"),
+    ("As", "11131221131211322113322112", "Ge.Na"),
+    ("Se", "13211321222113222112", "As"),
+    ("Br", "3113112211322112", "Se"),
+    ("Kr", "11131221222112", "Br"),
+    ("Rb", "1321122112", "Kr"),
+    ("Sr", "3112112", "Rb"),
+    ("Y", "1112133", "Sr.U"),
+    ("Zr", "12322211331222113112211", "Y.H.Ca.Tc"),
+    ("Nb", "1113122113322113111221131221", "Er.Zr"),
+    ("Mo", "13211322211312113211", "Nb"),
+    ("Tc", "311322113212221", "Mo"),
+    ("Ru", "132211331222113112211", "Eu.Ca.Tc"),
+    ("Rh", "311311222113111221131221", "Ho.Ru"),
+    ("Pd", "111312211312113211", "Rh"),
+    ("Ag", "132113212221", "Pd"),
+    ("Cd", "3113112211", "Ag"),
+    ("In", "11131221", "Cd"),
+    ("Sn", "13211", "In"),
+    ("Sb", "3112221", "Pm.Sn"),
+    ("Te", "1322113312211", "Eu.Ca.Sb"),
+    ("I", "311311222113111221", "Ho.Te"),
+    ("Xe", "11131221131211", "I"),
+    ("Cs", "13211321", "Xe"),
+    ("Ba", "311311", "Cs"),
+    ("La", "11131", "Ba"),
+    ("Ce", "1321133112", "La.H.Ca.Co"),
+    ("Pr", "31131112", "Ce"),
+    ("Nd", "111312", "Pr"),
+    ("Pm", "132", "Nd"),
+    ("Sm", "311332", "Pm.Ca.Zn"),
+    ("Eu", "1113222", "Sm"),
+    ("Gd", "13221133112", "Eu.Ca.Co"),
+    ("Tb", "3113112221131112", "Ho.Gd"),
+    ("Dy", "111312211312", "Tb"),
+    ("Ho", "1321132", "Dy"),
+    ("Er", "311311222", "Ho.Pm"),
+    ("Tm", "11131221133112", "Er.Ca.Co"),
+    ("Yb", "1321131112", "Tm"),
+    ("Lu", "311312", "Yb"),
+    ("Hf", "11132", "Lu"),
+    ("Ta", "13112221133211322112211213322113", "Hf.Pa.H.Ca.W"),
+    ("W", "312211322212221121123222113", "Ta"),
+    (
+        "Re",
+        "111312211312113221133211322112211213322113",
+        "Ge.Ca.W",
+    ),
+    ("Os", "1321132122211322212221121123222113", "Re"),
+    ("Ir", "3113112211322112211213322113", "Os"),
+    ("Pt", "111312212221121123222113", "Ir"),
+    ("Au", "132112211213322113", "Pt"),
+    ("Hg", "31121123222113", "Au"),
+    ("Tl", "111213322113", "Hg"),
+    ("Pb", "123222113", "Tl"),
+    ("Bi", "3113322113", "Pm.Pb"),
+    ("Po", "1113222113", "Bi"),
+    ("At", "1322113", "Po"),
+    ("Rn", "311311222113", "Ho.At"),
+    ("Fr", "1113122113", "Rn"),
+    ("Ra", "132113", "Fr"),
+    ("Ac", "3113", "Ra"),
+    ("Th", "1113", "Ac"),
+    ("Pa", "13", "Th"),
+    ("U", "3", "Pa"),
+];
+
+/// Upper bound on how many steps are simulated directly before giving up on splitting.
+const MAX_WARMUP_STEPS: usize = 24;
+
+/// What gets counted when describing a sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Counting {
+    /// Runs of the same symbol (look-and-say).
+    Runs,
+    /// All occurrences of each symbol, largest symbol first (Robinson's variant).
+    Totals,
+}
+
+fn to_digits(s: &str) -> Result<Vec<usize>, ()> {
+    to_symbols(s, 10)
+}
+
+/// Parses digits of the given base (up to 36, so `0-9a-z`).
+fn to_symbols(s: &str, base: usize) -> Result<Vec<usize>, ()> {
+    if !(2..=36).contains(&base) {
+        return Err(());
+    }
+    s.chars()
+        .map(|c| c.to_digit(base as u32).map(|d| d as usize).ok_or(()))
+        .collect()
+}
+
+/// Writes digits of the given base (up to 36), the other way around from [`to_symbols`].
+fn from_symbols(symbols: &[usize], base: usize) -> Option<String> {
+    if !(2..=36).contains(&base) {
+        return None;
+    }
+    symbols
+        .iter()
+        .map(|d| char::from_digit(u32::try_from(*d).ok()?, base as u32))
+        .collect()
 }
 
-impl<I, T> Iterator for Uniq<I, T>
+/// Writes `n` in the given base, most significant digit first.
+fn to_base(mut n: usize, base: usize) -> Vec<usize> {
+    assert!(base >= 2, "base should be at least 2");
+    let mut digits = vec![n % base];
+    n /= base;
+    while n > 0 {
+        digits.push(n % base);
+        n /= base;
+    }
+    digits.reverse();
+    digits
+}
+
+/// One step of a look-and-say like sequence where `write_count` turns counts into symbols.
+fn describe<T, F>(seq: &[T], counting: Counting, write_count: F) -> Vec<T>
 where
-    I: Iterator<Item = T>,
-    T: Eq,
+    T: Ord + Clone,
+    F: Fn(usize) -> Vec<T>,
 {
-    type Item = (usize, T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(current) = self.next.as_ref() {
-            let mut count = 1;
-            let mut new_next = self.iter.next();
-            while new_next
-                .as_ref()
-                .map(|new_next| current == new_next)
-                .unwrap_or_default()
-            {
-                count += 1;
-                new_next = self.iter.next();
-            }
-            mem::swap(&mut self.next, &mut new_next);
-            Some((count, new_next.expect("")))
-        } else {
-            None
+    let groups = match counting {
+        Counting::Runs => seq.iter().cloned().uniq().collect::<Vec<_>>(),
+        Counting::Totals => {
+            let mut sorted = seq.to_vec();
+            sorted.sort_by(|a, b| b.cmp(a));
+            sorted.into_iter().uniq().collect()
         }
+    };
+    let mut result = Vec::new();
+    for (count, symbol) in groups {
+        result.extend(write_count(count));
+        result.push(symbol);
     }
+    result
 }
 
-trait AddUniq<I, T> {
-    fn uniq(self) -> Uniq<I, T>;
+fn look_and_say(digits: &[usize]) -> Vec<usize> {
+    describe(digits, Counting::Runs, |count| to_base(count, 10))
 }
 
-impl<I, T> AddUniq<I, T> for I
+/// Lazily yields the digits of the sequence after `steps` steps, keeping only one pending
+/// digit per step in memory.
+fn look_and_say_iter<'a, I>(digits: I, steps: usize) -> Box<dyn Iterator<Item = usize> + 'a>
 where
-    I: Iterator<Item = T>,
+    I: Iterator<Item = usize> + 'a,
 {
-    fn uniq(mut self) -> Uniq<I, T> {
-        let next = self.next();
-        Uniq { iter: self, next }
+    let mut iter: Box<dyn Iterator<Item = usize> + 'a> = Box::new(digits);
+    for _ in 0..steps {
+        iter = Box::new(
+            iter.uniq()
+                .flat_map(|(count, digit)| to_base(count, 10).into_iter().chain([digit])),
+        );
     }
+    iter
 }
 
-fn to_digits(s: &str) -> Result<Vec<usize>, ()> {
-    s.chars()
-        .map(|c| c.to_digit(10).map(|d| d as usize).ok_or(()))
-        .collect()
+/// Tracks look-and-say sequences as counts of Conway's elements (cosmological theorem).
+struct Elements {
+    digits: Vec<Vec<usize>>,
+    decays: Vec<Vec<usize>>,
+    /// Pairs of elements that never interact when next to each other.
+    stable_pairs: HashSet<(usize, usize)>,
 }
 
-fn look_and_say(digits: &[usize]) -> Vec<usize> {
-    let mut result = Vec::new();
-    for (count, digit) in digits.iter().cloned().uniq() {
-        result.push(count);
-        result.push(digit);
+impl Elements {
+    fn new() -> Self {
+        let names: HashMap<&str, usize> = ELEMENTS
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _, _))| (*name, idx))
+            .collect();
+        let digits = ELEMENTS
+            .iter()
+            .map(|(_, digits, _)| to_digits(digits).expect("elements should be just digits"))
+            .collect::<Vec<_>>();
+        let decays = ELEMENTS
+            .iter()
+            .map(|(_, _, decay)| {
+                decay
+                    .split('.')
+                    .map(|name| *names.get(name).expect("decay should name an element"))
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+
+        // start with pairs that don't interact in the next step and drop those that lead
+        // to an interacting pair later on
+        let mut stable_pairs = HashSet::new();
+        for left in 0..digits.len() {
+            for right in 0..digits.len() {
+                let together = [digits[left].as_slice(), digits[right].as_slice()].concat();
+                let separately =
+                    [look_and_say(&digits[left]), look_and_say(&digits[right])].concat();
+                if look_and_say(&together) == separately {
+                    stable_pairs.insert((left, right));
+                }
+            }
+        }
+        loop {
+            let unstable = stable_pairs
+                .iter()
+                .filter(|(left, right)| {
+                    let decayed = [decays[*left].as_slice(), decays[*right].as_slice()].concat();
+                    decayed
+                        .windows(2)
+                        .any(|pair| !stable_pairs.contains(&(pair[0], pair[1])))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if unstable.is_empty() {
+                break;
+            }
+            for pair in unstable {
+                stable_pairs.remove(&pair);
+            }
+        }
+
+        Self {
+            digits,
+            decays,
+            stable_pairs,
+        }
+    }
+
+    /// Splits the sequence into elements that evolve independently.
+    fn split(&self, digits: &[usize]) -> Option<Vec<usize>> {
+        let mut failed = HashSet::new();
+        let mut result = Vec::new();
+        if self.split_from(digits, None, &mut failed, &mut result) {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    fn split_from(
+        &self,
+        digits: &[usize],
+        previous: Option<usize>,
+        failed: &mut HashSet<(usize, Option<usize>)>,
+        result: &mut Vec<usize>,
+    ) -> bool {
+        if digits.is_empty() {
+            return true;
+        }
+        if failed.contains(&(digits.len(), previous)) {
+            return false;
+        }
+        for (element, element_digits) in self.digits.iter().enumerate() {
+            if digits.starts_with(element_digits)
+                && previous
+                    .map(|previous| self.stable_pairs.contains(&(previous, element)))
+                    .unwrap_or(true)
+            {
+                result.push(element);
+                if self.split_from(
+                    &digits[element_digits.len()..],
+                    Some(element),
+                    failed,
+                    result,
+                ) {
+                    return true;
+                }
+                result.pop();
+            }
+        }
+        failed.insert((digits.len(), previous));
+        false
+    }
+
+    fn step(&self, counts: &[usize]) -> Vec<usize> {
+        let mut new_counts = vec![0; counts.len()];
+        for (element, count) in counts.iter().enumerate() {
+            for decayed in &self.decays[element] {
+                new_counts[*decayed] += count;
+            }
+        }
+        new_counts
+    }
+
+    /// Length of the sequence after `steps` steps without building it.
+    ///
+    /// Returns `None` if the sequence doesn't split into elements after a few steps (for example
+    /// when it has digits above 3).
+    fn len_after(&self, digits: &[usize], steps: usize) -> Option<usize> {
+        let mut seq = digits.to_vec();
+        for done in 0..=steps.min(MAX_WARMUP_STEPS) {
+            if done == steps {
+                return Some(seq.len());
+            }
+            if let Some(split) = self.split(&seq) {
+                let mut counts = vec![0; self.digits.len()];
+                for element in split {
+                    counts[element] += 1;
+                }
+                for _ in done..steps {
+                    counts = self.step(&counts);
+                }
+                return Some(
+                    counts
+                        .iter()
+                        .zip(self.digits.iter())
+                        .map(|(count, digits)| count * digits.len())
+                        .sum(),
+                );
+            }
+            seq = look_and_say(&seq);
+        }
+        None
     }
-    result
 }
 
 fn main() {
-    let mut seq = to_digits(INPUT).expect("input should be just digits");
+    let seq = to_digits(INPUT).expect("input should be just digits");
+    let elements = Elements::new();
+    let len_after = |steps| {
+        elements
+            .len_after(&seq, steps)
+            .expect("input should split into elements")
+    };
+    println!("Part 1: {}", len_after(40));
+    println!("Part 2: {}", len_after(50));
+    println!("After 100 steps: {}", len_after(100));
+    let prefix = look_and_say_iter(seq.iter().cloned(), 100)
+        .take(30)
+        .map(|d| d.to_string())
+        .collect::<String>();
+    println!("After 100 steps starts with: {prefix}");
+    let mut totals = seq.clone();
     for _ in 0..40 {
-        seq = look_and_say(&seq);
-    }
-    println!("Part 1: {}", seq.len());
-    for _ in 0..10 {
-        seq = look_and_say(&seq);
+        totals = describe(&totals, Counting::Totals, |count| to_base(count, 10));
     }
-    println!("Part 2: {}", seq.len());
+    println!(
+        "Counting all digits instead: {}",
+        from_symbols(&totals, 10).expect("digits should be decimal")
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_uniq_empty() {
-        let v: Vec<usize> = Vec::new();
-        let mut iter = v.into_iter().uniq();
-        assert_eq!(iter.next(), None);
-    }
-
-    #[test]
-    fn test_uniq() {
-        let v = vec![1, 1, 2, 3, 3, 3, 1, 1];
-        let mut iter = v.into_iter().uniq();
-        assert_eq!(iter.next(), Some((2, 1)));
-        assert_eq!(iter.next(), Some((1, 2)));
-        assert_eq!(iter.next(), Some((3, 3)));
-        assert_eq!(iter.next(), Some((2, 1)));
-        assert_eq!(iter.next(), None);
-    }
-
     #[test]
     fn test_look_and_say() {
         assert_eq!(look_and_say(&[1]), vec![1, 1]);
@@ -105,4 +394,134 @@ mod tests {
         assert_eq!(look_and_say(&[1, 2, 1, 1]), vec![1, 1, 1, 2, 2, 1]);
         assert_eq!(look_and_say(&[1, 1, 1, 2, 2, 1]), vec![3, 1, 2, 2, 1, 1]);
     }
+
+    #[test]
+    fn test_long_runs() {
+        let seed = to_digits("1111111111").unwrap();
+        assert_eq!(look_and_say(&seed), vec![1, 0, 1]);
+        assert_eq!(look_and_say(&look_and_say(&seed)), vec![1, 1, 1, 0, 1, 1]);
+        assert_eq!(
+            look_and_say_iter(seed.iter().cloned(), 2).collect::<Vec<_>>(),
+            vec![1, 1, 1, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_bases() {
+        assert_eq!(to_base(0, 10), vec![0]);
+        assert_eq!(to_base(10, 10), vec![1, 0]);
+        assert_eq!(to_base(6, 2), vec![1, 1, 0]);
+        assert_eq!(to_base(35, 36), vec![35]);
+        assert_eq!(to_symbols("1z", 36), Ok(vec![1, 35]));
+        assert_eq!(to_symbols("12", 2), Err(()));
+        assert_eq!(to_symbols("12", 37), Err(()));
+        assert_eq!(to_digits("12a"), Err(()));
+        assert_eq!(from_symbols(&[1, 35], 36), Some("1z".to_owned()));
+        assert_eq!(from_symbols(&[2], 2), None);
+        assert_eq!(from_symbols(&[1], 40), None);
+        assert_eq!(from_symbols(&[0], 1), None);
+
+        let binary = |seq: &[usize]| describe(seq, Counting::Runs, |count| to_base(count, 2));
+        let seq = to_symbols("1", 2).unwrap();
+        let seq = binary(&seq);
+        assert_eq!(from_symbols(&seq, 2).unwrap(), "11");
+        let seq = binary(&seq);
+        assert_eq!(from_symbols(&seq, 2).unwrap(), "101");
+        let seq = binary(&seq);
+        assert_eq!(from_symbols(&seq, 2).unwrap(), "111011");
+    }
+
+    #[test]
+    fn test_generic_symbols() {
+        let seq = "aaabcc".chars().collect::<Vec<_>>();
+        let spelled = describe(&seq, Counting::Runs, |count| {
+            count.to_string().chars().collect()
+        });
+        assert_eq!(spelled.into_iter().collect::<String>(), "3a1b2c");
+    }
+
+    #[test]
+    fn test_robinson() {
+        let robinson = |seq: &[usize]| describe(seq, Counting::Totals, |count| to_base(count, 10));
+        let mut seq = vec![0];
+        for expected in ["10", "1110", "3110", "132110", "13123110", "23124110"] {
+            seq = robinson(&seq);
+            assert_eq!(from_symbols(&seq, 10).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_look_and_say_iter() {
+        let digits = to_digits(INPUT).unwrap();
+        let mut seq = digits.clone();
+        for steps in 0..15 {
+            assert_eq!(
+                look_and_say_iter(digits.iter().cloned(), steps).collect::<Vec<_>>(),
+                seq
+            );
+            seq = look_and_say(&seq);
+        }
+        assert_eq!(look_and_say_iter(digits.iter().cloned(), 25).count(), {
+            let mut seq = digits.clone();
+            for _ in 0..25 {
+                seq = look_and_say(&seq);
+            }
+            seq.len()
+        });
+        assert_eq!(look_and_say_iter(std::iter::empty(), 3).next(), None);
+    }
+
+    #[test]
+    fn test_look_and_say_iter_prefix() {
+        let elements = Elements::new();
+        let digits = to_digits(INPUT).unwrap();
+        let deep = look_and_say_iter(digits.iter().cloned(), 60);
+        let shallow = look_and_say_iter(digits.iter().cloned(), 30).collect::<Vec<_>>();
+        assert_eq!(shallow.len(), elements.len_after(&digits, 30).unwrap());
+        let prefix = deep.take(10).collect::<Vec<_>>();
+        let mut seq = shallow;
+        // truncating only garbles the tail so the start can be checked without the whole thing
+        for _ in 30..60 {
+            seq = look_and_say(&seq[..seq.len().min(1000)]);
+        }
+        assert_eq!(prefix, seq[..10]);
+    }
+
+    #[test]
+    fn test_elements_decay() {
+        let elements = Elements::new();
+        for (element, digits) in elements.digits.iter().enumerate() {
+            let decayed = elements.decays[element]
+                .iter()
+                .flat_map(|decayed| elements.digits[*decayed].iter().cloned())
+                .collect::<Vec<_>>();
+            assert_eq!(look_and_say(digits), decayed, "{}", ELEMENTS[element].0);
+            assert_eq!(elements.split(digits), Some(vec![element]));
+            for pair in elements.decays[element].windows(2) {
+                assert!(elements.stable_pairs.contains(&(pair[0], pair[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_elements_len_after() {
+        let elements = Elements::new();
+        for seed in ["1", "3", "22", "1211", "1113222", "3113322113", INPUT] {
+            let mut seq = to_digits(seed).unwrap();
+            for steps in 0..30 {
+                assert_eq!(
+                    elements.len_after(&to_digits(seed).unwrap(), steps),
+                    Some(seq.len())
+                );
+                seq = look_and_say(&seq);
+            }
+        }
+    }
+
+    #[test]
+    fn test_elements_unsplittable() {
+        let elements = Elements::new();
+        assert_eq!(elements.len_after(&[1, 4], 30), None);
+        assert_eq!(elements.len_after(&[1, 4], 2), Some(4));
+    }
 }
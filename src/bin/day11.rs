@@ -1,85 +1,767 @@
+use std::collections::HashMap;
+
 const INPUT: &str = "vzbxkghb";
 
-fn has_straight(s: &[u8]) -> bool {
-    for win in s.windows(3) {
-        if win[0] == win[1] + 1 && win[1] == win[2] + 1 {
-            return true;
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Error {
+    NotAscii(char),
+    RepeatedLetter(char),
+    EmptyAlphabet,
+    NotInAlphabet(char),
+    LengthMismatch,
+    NoValidPassword,
+    Overflow,
+}
+
+/// Ordered set of ASCII characters passwords are made of. Straights follow this order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Alphabet {
+    letters: Vec<u8>,
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::new("abcdefghijklmnopqrstuvwxyz").expect("lowercase letters should be an alphabet")
+    }
+}
+
+impl Alphabet {
+    fn new(letters: &str) -> Result<Self, Error> {
+        let mut result = Vec::new();
+        for c in letters.chars() {
+            let letter = u8::try_from(c)
+                .ok()
+                .filter(u8::is_ascii)
+                .ok_or(Error::NotAscii(c))?;
+            if result.contains(&letter) {
+                return Err(Error::RepeatedLetter(c));
+            }
+            result.push(letter);
         }
+        if result.is_empty() {
+            return Err(Error::EmptyAlphabet);
+        }
+        Ok(Self { letters: result })
+    }
+
+    fn position(&self, c: u8) -> Option<usize> {
+        self.letters.iter().position(|letter| *letter == c)
+    }
+
+    fn successor(&self, c: u8) -> Option<u8> {
+        self.letters.get(self.position(c)? + 1).cloned()
+    }
+
+    fn first(&self) -> u8 {
+        self.letters[0]
+    }
+
+    /// Letters from `c` (included) onwards.
+    fn from(&self, c: u8) -> &[u8] {
+        &self.letters[self.position(c).unwrap_or(self.letters.len())..]
     }
-    false
 }
 
-fn valid_chars(s: &[u8]) -> bool {
-    !s.contains(&b'i') && !s.contains(&b'o') && !s.contains(&b'l')
+/// A single password requirement. Passwords are stored with the last letter first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Rule {
+    /// At least this many increasing letters in a row, like `abc`.
+    Straight(usize),
+    /// None of these letters.
+    Forbidden(Vec<u8>),
+    /// At least this many different letters appearing as a pair, like `aa`.
+    Pairs(usize),
+    MinLength(usize),
 }
 
-fn has_2_pairs(s: &[u8]) -> bool {
-    let mut first_pair = None;
-    for pair in s.windows(2) {
-        if pair[0] == pair[1] {
-            if let Some(x) = first_pair {
-                if x != pair[0] {
+impl Rule {
+    fn check(&self, s: &[u8], alphabet: &Alphabet) -> bool {
+        match self {
+            Self::Straight(len) => has_straight(s, *len, alphabet),
+            Self::Forbidden(letters) => valid_chars(s, letters),
+            Self::Pairs(count) => has_pairs(s, *count),
+            Self::MinLength(len) => s.len() >= *len,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Policy {
+    alphabet: Alphabet,
+    rules: Vec<Rule>,
+}
+
+impl Default for Policy {
+    /// Santa's rules from the puzzle.
+    fn default() -> Self {
+        Self {
+            alphabet: Alphabet::default(),
+            rules: vec![
+                Rule::Straight(3),
+                Rule::Forbidden(b"iol".to_vec()),
+                Rule::Pairs(2),
+                Rule::MinLength(8),
+            ],
+        }
+    }
+}
+
+impl Policy {
+    /// Rules the password doesn't satisfy.
+    fn failures(&self, s: &[u8]) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.check(s, &self.alphabet))
+            .collect()
+    }
+
+    fn is_valid(&self, s: &[u8]) -> bool {
+        self.failures(s).is_empty()
+    }
+
+    /// Whether `free` more letters in front of `fixed` could possibly make a valid password.
+    ///
+    /// Never rejects a prefix that can be completed but may accept some that can't.
+    fn can_complete(&self, fixed: &[u8], free: usize) -> bool {
+        self.rules.iter().all(|rule| match rule {
+            Rule::Straight(len) => {
+                if has_straight(fixed, *len, &self.alphabet) {
                     return true;
                 }
-            } else {
-                first_pair = Some(pair[0]);
+                // letters at the end of `fixed` that a straight could continue from
+                let run = if fixed
+                    .first()
+                    .and_then(|c| self.alphabet.successor(*c))
+                    .is_some()
+                {
+                    1 + fixed
+                        .windows(2)
+                        .take_while(|pair| self.alphabet.successor(pair[1]) == Some(pair[0]))
+                        .count()
+                } else {
+                    0
+                };
+                free + run >= *len
+            }
+            Rule::Forbidden(letters) => valid_chars(fixed, letters),
+            Rule::Pairs(count) => {
+                let pairs = pair_letters(fixed);
+                let missing = count.saturating_sub(pairs.len());
+                let reusable = fixed
+                    .first()
+                    .map(|c| !pairs.contains(c))
+                    .unwrap_or_default();
+                missing == 0 || free + usize::from(reusable) >= 2 * missing
             }
+            Rule::MinLength(len) => fixed.len() + free >= *len,
+        })
+    }
+}
+
+fn has_straight(s: &[u8], len: usize, alphabet: &Alphabet) -> bool {
+    if len <= 1 {
+        return s.len() >= len;
+    }
+    for win in s.windows(len) {
+        if win
+            .windows(2)
+            .all(|pair| alphabet.successor(pair[1]) == Some(pair[0]))
+        {
+            return true;
         }
     }
     false
 }
 
-fn next_pass(p: &mut [u8]) {
+fn valid_chars(s: &[u8], forbidden: &[u8]) -> bool {
+    !s.iter().any(|c| forbidden.contains(c))
+}
+
+fn has_pairs(s: &[u8], count: usize) -> bool {
+    pair_letters(s).len() >= count
+}
+
+fn pair_letters(s: &[u8]) -> Vec<u8> {
+    let mut pairs = Vec::new();
+    for pair in s.windows(2) {
+        if pair[0] == pair[1] && !pairs.contains(&pair[0]) {
+            pairs.push(pair[0]);
+        }
+    }
+    pairs
+}
+
+/// Returns `false` if it wrapped around from `z...z` to `a...a`.
+fn next_pass(p: &mut [u8], alphabet: &Alphabet) -> bool {
     for c in p.iter_mut() {
-        *c += 1;
-        if *c > b'z' {
-            *c = b'a';
-        } else {
-            return;
+        if let Some(next) = alphabet.successor(*c) {
+            *c = next;
+            return true;
         }
+        *c = alphabet.first();
     }
+    false
 }
 
-fn next_valid_pass(p: &mut [u8]) {
-    next_pass(p);
-    while !has_straight(p) || !valid_chars(p) || !has_2_pairs(p) {
-        next_pass(p);
+/// Moves to the next valid password, wrapping around after `z...z` like `next_pass` does.
+///
+/// Returns `false` if there's no valid password of this length at all.
+fn next_valid_pass(p: &mut [u8], policy: &Policy) -> bool {
+    let mut lowest = p.to_vec();
+    next_pass(&mut lowest, &policy.alphabet);
+    fill_valid_pass(p, p.len(), Some(&lowest), policy) || fill_valid_pass(p, p.len(), None, policy)
+}
+
+/// Fills the first `free` letters (the least significant ones) with the smallest letters that
+/// make the password valid, not going below `lowest` if given.
+///
+/// Letters are picked from the most significant one and a prefix is abandoned as soon as the
+/// policy says it can't be completed, so forbidden letters and hopeless prefixes are skipped
+/// as a whole.
+fn fill_valid_pass(p: &mut [u8], free: usize, lowest: Option<&[u8]>, policy: &Policy) -> bool {
+    if free == 0 {
+        return policy.is_valid(p);
     }
+    let idx = free - 1;
+    let start = lowest
+        .map(|lowest| lowest[idx])
+        .unwrap_or(policy.alphabet.first());
+    for c in policy.alphabet.from(start).iter().cloned() {
+        p[idx] = c;
+        if policy.can_complete(&p[idx..], idx)
+            && fill_valid_pass(p, idx, lowest.filter(|_| c == start), policy)
+        {
+            return true;
+        }
+    }
+    false
 }
 
-fn str2pass(s: &str) -> Option<Vec<u8>> {
-    s.chars().rev().map(|c| u8::try_from(c).ok()).collect()
+fn str2pass(s: &str, alphabet: &Alphabet) -> Result<Vec<u8>, Error> {
+    s.chars()
+        .rev()
+        .map(|c| {
+            u8::try_from(c)
+                .ok()
+                .filter(|letter| alphabet.position(*letter).is_some())
+                .ok_or(Error::NotInAlphabet(c))
+        })
+        .collect()
 }
 
 fn pass2str(p: &[u8]) -> String {
     p.iter().rev().map(|d| char::from(*d)).collect()
 }
 
-fn find_next_valid_pass(s: &str) -> String {
-    let mut raw_pass = str2pass(s).expect("must be a-z");
-    next_valid_pass(&mut raw_pass);
-    pass2str(&raw_pass)
+fn find_next_valid_pass(s: &str, policy: &Policy) -> Result<String, Error> {
+    let mut raw_pass = str2pass(s, &policy.alphabet)?;
+    if next_valid_pass(&mut raw_pass, policy) {
+        Ok(pass2str(&raw_pass))
+    } else {
+        Err(Error::NoValidPassword)
+    }
+}
+
+/// Valid passwords following a seed in order, up to `z...z`.
+struct ValidPasswords<'a> {
+    /// Smallest candidate for the next password.
+    lowest: Option<Vec<u8>>,
+    policy: &'a Policy,
+}
+
+impl<'a> ValidPasswords<'a> {
+    fn new(seed: &str, policy: &'a Policy) -> Result<Self, Error> {
+        let mut lowest = str2pass(seed, &policy.alphabet)?;
+        let lowest = if next_pass(&mut lowest, &policy.alphabet) {
+            Some(lowest)
+        } else {
+            None
+        };
+        Ok(Self { lowest, policy })
+    }
+}
+
+impl Iterator for ValidPasswords<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lowest = self.lowest.take()?;
+        let mut p = lowest.clone();
+        if !fill_valid_pass(&mut p, lowest.len(), Some(&lowest), self.policy) {
+            return None;
+        }
+        let result = pass2str(&p);
+        if next_pass(&mut p, &self.policy.alphabet) {
+            self.lowest = Some(p);
+        }
+        Some(result)
+    }
+}
+
+/// The policy as an automaton reading passwords from the first letter, used to count valid
+/// passwords without listing them.
+struct Automaton {
+    alphabet: Alphabet,
+    forbidden: Vec<u8>,
+    straights: Vec<usize>,
+    pairs: Vec<usize>,
+    min_length: usize,
+    /// Longest straight that's worth tracking.
+    max_straight: usize,
+    /// Most pairs that are worth tracking.
+    max_pairs: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct AutomatonState {
+    last: Option<u8>,
+    run: usize,
+    longest_run: usize,
+    /// Bitmask of letters seen as pairs, all ones once there are enough of them.
+    pairs: u128,
+}
+
+impl Automaton {
+    fn new(policy: &Policy) -> Self {
+        let mut automaton = Self {
+            alphabet: policy.alphabet.clone(),
+            forbidden: Vec::new(),
+            straights: Vec::new(),
+            pairs: Vec::new(),
+            min_length: 0,
+            max_straight: 0,
+            max_pairs: 0,
+        };
+        for rule in &policy.rules {
+            match rule {
+                Rule::Straight(len) => automaton.straights.push(*len),
+                Rule::Forbidden(letters) => automaton.forbidden.extend(letters),
+                Rule::Pairs(count) => automaton.pairs.push(*count),
+                Rule::MinLength(len) => automaton.min_length = automaton.min_length.max(*len),
+            }
+        }
+        automaton.max_straight = automaton
+            .straights
+            .iter()
+            .cloned()
+            .max()
+            .unwrap_or_default();
+        automaton.max_pairs = automaton.pairs.iter().cloned().max().unwrap_or_default();
+        automaton
+    }
+
+    fn letters(&self) -> impl Iterator<Item = u8> + '_ {
+        self.alphabet
+            .letters
+            .iter()
+            .cloned()
+            .filter(|c| !self.forbidden.contains(c))
+    }
+
+    fn step(&self, state: AutomatonState, c: u8) -> AutomatonState {
+        let run = if state.last.and_then(|last| self.alphabet.successor(last)) == Some(c) {
+            (state.run + 1).min(self.max_straight)
+        } else {
+            1.min(self.max_straight)
+        };
+        let mut pairs = state.pairs;
+        if state.last == Some(c) {
+            pairs |= 1 << c;
+            if pairs.count_ones() as usize >= self.max_pairs {
+                pairs = u128::MAX;
+            }
+        }
+        AutomatonState {
+            last: Some(c),
+            run,
+            longest_run: state.longest_run.max(run),
+            pairs,
+        }
+    }
+
+    fn accepts(&self, state: AutomatonState, len: usize) -> bool {
+        len >= self.min_length
+            && self.straights.iter().all(|straight| {
+                state.longest_run >= *straight || (*straight <= 1 && len >= *straight)
+            })
+            && self
+                .pairs
+                .iter()
+                .all(|count| state.pairs.count_ones() as usize >= *count)
+    }
+
+    /// Number of ways to finish a password of length `len` with `remaining` more letters, or
+    /// `None` if it doesn't fit in a `u128`.
+    fn count_completions(
+        &self,
+        state: AutomatonState,
+        remaining: usize,
+        len: usize,
+        memo: &mut HashMap<(AutomatonState, usize), u128>,
+    ) -> Option<u128> {
+        if remaining == 0 {
+            return Some(u128::from(self.accepts(state, len)));
+        }
+        if let Some(count) = memo.get(&(state, remaining)) {
+            return Some(*count);
+        }
+        let mut count: u128 = 0;
+        for c in self.letters() {
+            let completions =
+                self.count_completions(self.step(state, c), remaining - 1, len, memo)?;
+            count = count.checked_add(completions)?;
+        }
+        memo.insert((state, remaining), count);
+        Some(count)
+    }
+
+    /// Number of valid passwords of the same length that aren't greater than `bound` (given
+    /// first letter first), or `None` if it doesn't fit in a `u128`.
+    fn count_up_to(&self, bound: &[u8]) -> Option<u128> {
+        let mut memo = HashMap::new();
+        let mut total: u128 = 0;
+        let mut state = AutomatonState::default();
+        for (idx, b) in bound.iter().enumerate() {
+            let remaining = bound.len() - idx - 1;
+            let below = &self.alphabet.letters[..self.alphabet.position(*b).unwrap_or_default()];
+            for c in below
+                .iter()
+                .cloned()
+                .filter(|c| !self.forbidden.contains(c))
+            {
+                let completions =
+                    self.count_completions(self.step(state, c), remaining, bound.len(), &mut memo)?;
+                total = total.checked_add(completions)?;
+            }
+            if self.forbidden.contains(b) {
+                return Some(total);
+            }
+            state = self.step(state, *b);
+        }
+        total.checked_add(u128::from(self.accepts(state, bound.len())))
+    }
+}
+
+/// Number of valid passwords from `from` to `to` (both included).
+///
+/// Fails with [`Error::Overflow`] when there are more than `u128::MAX` passwords up to `to`,
+/// which with the default policy happens from 30 letters on.
+fn count_valid_between(from: &str, to: &str, policy: &Policy) -> Result<u128, Error> {
+    let from_pass = str2pass(from, &policy.alphabet)?;
+    let to_pass = str2pass(to, &policy.alphabet)?;
+    if from_pass.len() != to_pass.len() {
+        return Err(Error::LengthMismatch);
+    }
+    let order = |p: &[u8]| {
+        p.iter()
+            .rev()
+            .map(|c| policy.alphabet.position(*c))
+            .collect::<Vec<_>>()
+    };
+    if order(&from_pass) > order(&to_pass) {
+        return Ok(0);
+    }
+    let automaton = Automaton::new(policy);
+    let up_to = automaton
+        .count_up_to(to.as_bytes())
+        .ok_or(Error::Overflow)?;
+    let below = automaton
+        .count_up_to(from.as_bytes())
+        .ok_or(Error::Overflow)?;
+    Ok(up_to - below + u128::from(policy.is_valid(&from_pass)))
 }
 
 fn main() {
-    let first = find_next_valid_pass(INPUT);
+    let policy = Policy::default();
+    let first = find_next_valid_pass(INPUT, &policy).expect("input should be a-z");
     println!("Part 1: {}", &first);
-    let second = find_next_valid_pass(&first);
+    let second = find_next_valid_pass(&first, &policy).expect("there should be another password");
     println!("Part 2: {}", &second);
+    let upcoming = ValidPasswords::new(&second, &policy)
+        .expect("password should be a-z")
+        .take(3)
+        .collect::<Vec<_>>();
+    println!("Then: {}", upcoming.join(", "));
+    println!(
+        "Valid passwords after that: {}",
+        count_valid_between(&second, "zzzzzzzz", &policy).expect("password should be a-z") - 1
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pass(s: &str) -> Vec<u8> {
+        str2pass(s, &Alphabet::default()).unwrap()
+    }
+
+    fn brute_force_next_valid_pass(s: &str, policy: &Policy) -> String {
+        let mut p = str2pass(s, &policy.alphabet).unwrap();
+        next_pass(&mut p, &policy.alphabet);
+        while !policy.is_valid(&p) {
+            next_pass(&mut p, &policy.alphabet);
+        }
+        pass2str(&p)
+    }
+
     #[test]
     fn test_sample_validation_failure() {
-        assert!(has_straight(&str2pass("abcdffaa").unwrap()));
+        assert!(has_straight(&pass("abcdffaa"), 3, &Alphabet::default()));
     }
 
     #[test]
     fn test_sample() {
-        assert_eq!(find_next_valid_pass("abcdefgh"), "abcdffaa");
-        assert_eq!(find_next_valid_pass("ghijklmn"), "ghjaabcc");
+        let policy = Policy::default();
+        assert_eq!(
+            find_next_valid_pass("abcdefgh", &policy).unwrap(),
+            "abcdffaa"
+        );
+        assert_eq!(
+            find_next_valid_pass("ghijklmn", &policy).unwrap(),
+            "ghjaabcc"
+        );
+    }
+
+    #[test]
+    fn test_sample_failures() {
+        let policy = Policy::default();
+        assert_eq!(
+            policy.failures(&pass("hijklmmn")),
+            vec![&Rule::Forbidden(b"iol".to_vec()), &Rule::Pairs(2)]
+        );
+        assert_eq!(policy.failures(&pass("abbceffg")), vec![&Rule::Straight(3)]);
+        assert_eq!(
+            policy.failures(&pass("abbcegjk")),
+            vec![&Rule::Straight(3), &Rule::Pairs(2)]
+        );
+        assert!(policy.failures(&pass("abcdffaa")).is_empty());
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let policy = Policy {
+            rules: vec![
+                Rule::Straight(4),
+                Rule::Forbidden(b"xyz".to_vec()),
+                Rule::Pairs(1),
+                Rule::MinLength(8),
+            ],
+            ..Default::default()
+        };
+        assert!(policy.is_valid(&pass("abcdeeff")));
+        assert_eq!(
+            policy.failures(&pass("abcxee")),
+            vec![
+                &Rule::Straight(4),
+                &Rule::Forbidden(b"xyz".to_vec()),
+                &Rule::MinLength(8)
+            ]
+        );
+        assert_eq!(
+            find_next_valid_pass("aaaaaaaa", &policy).unwrap(),
+            "aaaaabcd"
+        );
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let policy = Policy {
+            rules: vec![
+                Rule::Straight(3),
+                Rule::Forbidden(b"iol".to_vec()),
+                Rule::Pairs(1),
+            ],
+            ..Default::default()
+        };
+        for s in [
+            "aaaaa", "abcde", "zzzzz", "zzzzy", "hijkl", "xyzzz", "lmnop", "ghioa", "abbcc",
+            "qqrst",
+        ] {
+            assert_eq!(
+                find_next_valid_pass(s, &policy).unwrap(),
+                brute_force_next_valid_pass(s, &policy),
+                "{s}"
+            );
+        }
+
+        let default = Policy::default();
+        for s in ["abcdffaa", "abcdffzz", "zzzzzzzz"] {
+            assert_eq!(
+                find_next_valid_pass(s, &default).unwrap(),
+                brute_force_next_valid_pass(s, &default),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_long_passwords() {
+        let policy = Policy::default();
+        assert_eq!(
+            find_next_valid_pass("abcdefghijklmnop", &policy).unwrap(),
+            "abcdefghjaaaaabb"
+        );
+        assert_eq!(
+            find_next_valid_pass("zzzzzzzzzzzzzzzzzzzz", &policy).unwrap(),
+            "aaaaaaaaaaaaaaaaabcc"
+        );
+        assert_eq!(
+            find_next_valid_pass("ghijklmnopqrstuvwxyz", &policy).unwrap(),
+            "ghjaaaaaaaaaaaaaabcc"
+        );
+    }
+
+    #[test]
+    fn test_valid_passwords() {
+        let policy = Policy::default();
+        let passwords = ValidPasswords::new("abcdefgh", &policy)
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(passwords, vec!["abcdffaa", "abcdffbb", "abcdffcc"]);
+
+        let mut expected = Vec::new();
+        let mut s = "ghjaabcc".to_owned();
+        for _ in 0..5 {
+            s = find_next_valid_pass(&s, &policy).unwrap();
+            expected.push(s.clone());
+        }
+        let passwords = ValidPasswords::new("ghjaabcc", &policy)
+            .unwrap()
+            .take(5)
+            .collect::<Vec<_>>();
+        assert_eq!(passwords, expected);
+
+        // no wrapping around at the end
+        let policy = Policy {
+            rules: vec![Rule::Straight(3)],
+            ..Default::default()
+        };
+        let passwords = ValidPasswords::new("zzzxyy", &policy)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(passwords, vec!["zzzxyz"]);
+        assert_eq!(ValidPasswords::new("zzzzzz", &policy).unwrap().next(), None);
+    }
+
+    #[test]
+    fn test_count_valid_between() {
+        let policy = Policy {
+            rules: vec![
+                Rule::Straight(3),
+                Rule::Forbidden(b"iol".to_vec()),
+                Rule::Pairs(1),
+            ],
+            ..Default::default()
+        };
+        for (from, to) in [("aaaaa", "azzzz"), ("hijkl", "hzzaa"), ("abcdd", "abcdd")] {
+            let mut count = u128::from(policy.is_valid(&pass(from)));
+            count += ValidPasswords::new(from, &policy)
+                .unwrap()
+                .take_while(|s| s.as_str() <= to)
+                .count() as u128;
+            assert_eq!(
+                count_valid_between(from, to, &policy),
+                Ok(count),
+                "{from}..{to}"
+            );
+        }
+        assert_eq!(count_valid_between("b", "a", &policy), Ok(0));
+        assert_eq!(
+            count_valid_between("ab", "abc", &policy),
+            Err(Error::LengthMismatch)
+        );
+        assert_eq!(
+            count_valid_between("aB", "ab", &policy),
+            Err(Error::NotInAlphabet('B'))
+        );
+
+        let policy = Policy::default();
+        assert_eq!(count_valid_between("abcdffaa", "abcdffcc", &policy), Ok(3));
+        // far too many to list
+        let (from, to) = ("a".repeat(20), "z".repeat(20));
+        assert!(count_valid_between(&from, &to, &policy).unwrap() > u128::from(u64::MAX));
+
+        // every password counts, so there are 26^len of them
+        let policy = Policy {
+            rules: Vec::new(),
+            ..Default::default()
+        };
+        let (from, to) = ("a".repeat(27), "z".repeat(27));
+        assert_eq!(count_valid_between(&from, &to, &policy), Ok(26u128.pow(27)));
+        let (from, to) = ("a".repeat(28), "z".repeat(28));
+        assert_eq!(
+            count_valid_between(&from, &to, &policy),
+            Err(Error::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_impossible_policy() {
+        let policy = Policy {
+            rules: vec![Rule::Straight(5), Rule::Pairs(2)],
+            ..Default::default()
+        };
+        let mut p = pass("abcdef");
+        assert!(!next_valid_pass(&mut p, &policy));
+    }
+
+    #[test]
+    fn test_alphabet() {
+        assert_eq!(Alphabet::new(""), Err(Error::EmptyAlphabet));
+        assert_eq!(Alphabet::new("abca"), Err(Error::RepeatedLetter('a')));
+        assert_eq!(Alphabet::new("abć"), Err(Error::NotAscii('ć')));
+
+        let alphabet = Alphabet::new("abcdefghijklmnopqrstuvwxyz0123456789").unwrap();
+        assert_eq!(str2pass("ab1", &alphabet), Ok(b"1ba".to_vec()));
+        assert_eq!(str2pass("aB1", &alphabet), Err(Error::NotInAlphabet('B')));
+        assert_eq!(
+            find_next_valid_pass("ab1", &Policy::default()),
+            Err(Error::NotInAlphabet('1'))
+        );
+
+        let policy = Policy {
+            alphabet,
+            ..Default::default()
+        };
+        assert_eq!(
+            find_next_valid_pass("abcdffaa", &policy).unwrap(),
+            "abcdffbb"
+        );
+        assert_eq!(
+            find_next_valid_pass("xyz00000", &policy).unwrap(),
+            "xyz00011"
+        );
+        assert_eq!(
+            find_next_valid_pass("aabbcz99", &policy).unwrap(),
+            "aabbc012"
+        );
+        assert!(policy.is_valid(&str2pass("yz01aa22", &policy.alphabet).unwrap()));
+        assert_eq!(brute_force_next_valid_pass("xyz00000", &policy), "xyz00011");
+    }
+
+    #[test]
+    fn test_custom_alphabet() {
+        // a reversed alphabet makes `cba` a straight
+        let policy = Policy {
+            alphabet: Alphabet::new("zyxwvutsrqponmlkjihgfedcba").unwrap(),
+            rules: vec![Rule::Straight(3), Rule::Pairs(1)],
+        };
+        assert!(policy.is_valid(&str2pass("cbaa", &policy.alphabet).unwrap()));
+        assert!(!policy.is_valid(&str2pass("abcc", &policy.alphabet).unwrap()));
+        assert_eq!(find_next_valid_pass("zzzz", &policy).unwrap(), "zzyx");
+        assert_eq!(find_next_valid_pass("aaaa", &policy).unwrap(), "zzyx");
+        for s in ["zzzz", "ffff", "qqrs", "abab"] {
+            assert_eq!(
+                find_next_valid_pass(s, &policy).unwrap(),
+                brute_force_next_valid_pass(s, &policy),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            count_valid_between("zzzz", "aaaa", &policy).unwrap(),
+            ValidPasswords::new("zzzz", &policy).unwrap().count() as u128
+        );
     }
 }
@@ -1,18 +1,69 @@
 use std::collections::HashMap;
 
+#[derive(Clone)]
 struct Happiness<'a> {
     persons: Vec<&'a str>,
     happiness: HashMap<(&'a str, &'a str), isize>,
+    constraints: Vec<Constraint<'a>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Constraint<'a> {
+    NextTo(&'a str, &'a str),
+    NotNextTo(&'a str, &'a str),
+    /// Seats are numbered around the table starting from 0.
+    Seat(&'a str, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Seat<'a> {
+    person: &'a str,
+    /// Happiness the person gets from the neighbour on the left (previous seat).
+    from_left: isize,
+    /// Happiness the person gets from the neighbour on the right (next seat).
+    from_right: isize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Arrangement<'a> {
+    seats: Vec<Seat<'a>>,
+    total: isize,
+}
+
+/// Everyone seated at several round tables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Tables<'a> {
+    tables: Vec<Arrangement<'a>>,
+    total: isize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SeatingError {
+    /// More guests than the search can keep track of.
+    TooManyGuests,
+    NotEnoughSeats,
+    /// The constraints can't be met.
+    Infeasible,
 }
 
 impl<'a> Happiness<'a> {
-    const SELF: &'a str = "";
+    /// Seating everyone at one table keeps a value per set of guests and per guest, about
+    /// 80 MB for 20 guests.
+    const MAX_GUESTS: usize = 20;
+    /// The table search keeps a value per set of guests and per guest, and goes through all
+    /// the ways to split each set in two.
+    const MAX_TABLE_GUESTS: usize = 16;
 
     fn parse(s: &'a str) -> Result<Happiness<'a>, ()> {
         // 0     1     2    3  4         5     6  7       8    9  10
         // Alice would gain 54 happiness units by sitting next to Bob.
         // Alice would lose 79 happiness units by sitting next to Carol.
+        // and constraints:
+        // Alice must sit next to Bob.
+        // Carol must not sit next to David.
+        // Eric sits at seat 0.
         let mut happiness = HashMap::new();
+        let mut constraints = Vec::new();
         let mut persons = Vec::new();
         let mut add_person = |person: &'a str| {
             if !persons.contains(&person) {
@@ -22,6 +73,34 @@ impl<'a> Happiness<'a> {
 
         for line in s.lines() {
             let parts = line.split_whitespace().collect::<Vec<_>>();
+            // the full stop is optional on constraints
+            let name = |s: &'a str| s.strip_suffix('.').unwrap_or(s);
+            let constraint = match parts.as_slice() {
+                [person1, "must", "sit", "next", "to", person2] => {
+                    Some(Constraint::NextTo(person1, name(person2)))
+                }
+                [person1, "must", "not", "sit", "next", "to", person2] => {
+                    Some(Constraint::NotNextTo(person1, name(person2)))
+                }
+                [person, "sits", "at", "seat", seat] => Some(Constraint::Seat(
+                    person,
+                    name(seat).parse::<usize>().map_err(|_| ())?,
+                )),
+                _ => None,
+            };
+            if let Some(constraint) = constraint {
+                match constraint {
+                    Constraint::NextTo(person1, person2)
+                    | Constraint::NotNextTo(person1, person2) => {
+                        add_person(person1);
+                        add_person(person2);
+                    }
+                    Constraint::Seat(person, _) => add_person(person),
+                }
+                constraints.push(constraint);
+                continue;
+            }
+
             if parts.len() < 11 {
                 return Err(());
             }
@@ -39,7 +118,11 @@ impl<'a> Happiness<'a> {
             happiness.insert((person1, person2), delta_sign * delta);
         }
 
-        Ok(Happiness { persons, happiness })
+        Ok(Happiness {
+            persons,
+            happiness,
+            constraints,
+        })
     }
 
     fn get_happiness(&self, person1: &str, person2: &str) -> isize {
@@ -63,53 +146,1109 @@ impl<'a> Happiness<'a> {
         wrapped + in_order
     }
 
-    fn find_best_happiness(&self) -> isize {
-        let mut best = isize::MIN;
-        permutations(self.persons.clone(), |perm| {
-            let candidate = self.calculate_happiness(perm);
-            if best < candidate {
-                best = candidate;
+    /// Whether the seating (starting from seat 0) honours all the constraints.
+    fn satisfies_constraints(&self, order: &[&str]) -> bool {
+        let next_to = |person1: &str, person2: &str| {
+            (0..order.len()).any(|idx| {
+                let neighbour = order[(idx + 1) % order.len()];
+                order.len() > 1
+                    && ((order[idx] == person1 && neighbour == person2)
+                        || (order[idx] == person2 && neighbour == person1))
+            })
+        };
+        self.constraints.iter().all(|constraint| match constraint {
+            Constraint::NextTo(person1, person2) => next_to(person1, person2),
+            Constraint::NotNextTo(person1, person2) => !next_to(person1, person2),
+            Constraint::Seat(person, seat) => order.get(*seat) == Some(person),
+        })
+    }
+
+    fn find_best_happiness(&self) -> Result<isize, SeatingError> {
+        if self.persons.is_empty() {
+            return Ok(0);
+        }
+        Ok(self.calculate_happiness(&self.find_best_seating()?))
+    }
+
+    fn find_best_seating(&self) -> Result<Vec<&'a str>, SeatingError> {
+        Ok(self.find_best_seatings(1)?.swap_remove(0))
+    }
+
+    /// Explains where the happiness of a seating comes from.
+    fn arrangement(&self, order: &[&'a str]) -> Arrangement<'a> {
+        let get = |person, neighbour| {
+            self.happiness
+                .get(&(person, neighbour))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let seats = order
+            .iter()
+            .enumerate()
+            .map(|(idx, person)| {
+                let left = order[(idx + order.len() - 1) % order.len()];
+                let right = order[(idx + 1) % order.len()];
+                Seat {
+                    person,
+                    from_left: get(*person, left),
+                    from_right: get(*person, right),
+                }
+            })
+            .collect();
+        Arrangement {
+            seats,
+            total: self.calculate_happiness(order),
+        }
+    }
+
+    /// Up to `limit` of the best arrangements, not counting rotations and mirror images as
+    /// different (unless there are fixed seats).
+    fn find_best_arrangements(&self, limit: usize) -> Result<Vec<Arrangement<'a>>, SeatingError> {
+        if self.persons.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .find_best_seatings(limit)?
+            .iter()
+            .map(|order| self.arrangement(order))
+            .collect())
+    }
+
+    fn index(&self, person: &str) -> usize {
+        self.persons
+            .iter()
+            .position(|p| *p == person)
+            .expect("constraints should only mention known persons")
+    }
+
+    /// Happiness between each pair of persons by index, `None` for pairs that must not sit
+    /// together.
+    fn happiness_weights(&self) -> Vec<Vec<Option<isize>>> {
+        let mut weights = self
+            .persons
+            .iter()
+            .map(|p1| {
+                self.persons
+                    .iter()
+                    .map(|p2| Some(self.get_happiness(p1, p2)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for constraint in &self.constraints {
+            if let Constraint::NotNextTo(person1, person2) = constraint {
+                let (p1, p2) = (self.index(person1), self.index(person2));
+                weights[p1][p2] = None;
+                weights[p2][p1] = None;
             }
-        });
-        best
+        }
+        weights
+    }
+
+    /// Whether each pair of persons by index must sit together.
+    fn together(&self) -> Vec<Vec<bool>> {
+        let mut together = vec![vec![false; self.persons.len()]; self.persons.len()];
+        for constraint in &self.constraints {
+            if let Constraint::NextTo(person1, person2) = constraint {
+                let (p1, p2) = (self.index(person1), self.index(person2));
+                together[p1][p2] = true;
+                together[p2][p1] = true;
+            }
+        }
+        together
+    }
+
+    /// [`Self::happiness_weights`] with a big bonus for pairs that must sit together.
+    fn weights(&self) -> Vec<Vec<Option<isize>>> {
+        let bonus = 1 + 2 * self.happiness.values().map(|h| h.abs()).sum::<isize>();
+        let together = self.together();
+        let mut weights = self.happiness_weights();
+        for (p1, row) in weights.iter_mut().enumerate() {
+            for (p2, weight) in row.iter_mut().enumerate() {
+                if together[p1][p2] {
+                    *weight = weight.map(|w| w + bonus);
+                }
+            }
+        }
+        weights
+    }
+
+    /// Same as trying all the orders but the first person's seat is fixed (rotations don't
+    /// matter at a round table) and the rest is Held-Karp style dynamic programming over sets
+    /// of already seated people.
+    ///
+    /// Pairs that must sit together get a bonus bigger than any possible happiness so that the
+    /// best seatings use as many of them as possible, pairs that can't sit together are never
+    /// joined and people with fixed seats are only seated when their seat comes up.
+    ///
+    /// Returns up to `limit` of the best seatings starting from seat 0, without mirror images
+    /// when there are no fixed seats, and at least one. When lots of seatings tie there can be
+    /// factorially many of them, so keep `limit` small.
+    fn find_best_seatings(&self, limit: usize) -> Result<Vec<Vec<&'a str>>, SeatingError> {
+        let n = self.persons.len();
+        if n > Self::MAX_GUESTS {
+            return Err(SeatingError::TooManyGuests);
+        }
+
+        let mut seats = vec![None; n];
+        for constraint in &self.constraints {
+            if let Constraint::Seat(person, seat) = constraint {
+                let fixed = &mut seats[self.index(person)];
+                if fixed.is_some() && *fixed != Some(*seat) {
+                    return Err(SeatingError::Infeasible);
+                }
+                *fixed = Some(*seat);
+            }
+        }
+
+        let weights = self.weights();
+
+        let has_fixed_seats = seats.iter().any(Option::is_some);
+        // going around the other way is the same seating
+        let skip_mirrors = !has_fixed_seats && n >= 3;
+        let starts = if let Some(person) = seats.iter().position(|seat| *seat == Some(0)) {
+            vec![person]
+        } else if has_fixed_seats {
+            (0..n).filter(|p| seats[*p].is_none()).collect()
+        } else {
+            vec![0]
+        };
+
+        let mut best: Option<(isize, Vec<Vec<usize>>)> = None;
+        for start in starts {
+            if let Some((total, mut seatings)) =
+                best_circles(&weights, &seats, start, skip_mirrors, limit)
+            {
+                match best.as_mut() {
+                    Some((best_total, _)) if *best_total > total => {}
+                    Some((best_total, best_seatings)) if *best_total == total => {
+                        best_seatings.append(&mut seatings)
+                    }
+                    _ => best = Some((total, seatings)),
+                }
+            }
+        }
+
+        let seatings = best
+            .map(|(_, seatings)| seatings)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|seating| {
+                seating
+                    .into_iter()
+                    .map(|p| self.persons[p])
+                    .collect::<Vec<_>>()
+            })
+            // only fails if some pair that must sit together couldn't, and then all of them do
+            .filter(|order| self.satisfies_constraints(order))
+            .take(limit)
+            .collect::<Vec<_>>();
+        if seatings.is_empty() && limit > 0 {
+            return Err(SeatingError::Infeasible);
+        }
+        Ok(seatings)
+    }
+
+    /// Seats everyone at round tables taking at most `capacities[t]` people each (some seats
+    /// may stay empty), trying all the ways to split the guests between tables. That's fine
+    /// up to [`Self::MAX_TABLE_GUESTS`] people. Fixed seats are ignored since they don't say
+    /// which table.
+    fn find_best_tables(&self, capacities: &[usize]) -> Result<Tables<'a>, SeatingError> {
+        let n = self.persons.len();
+        if n > Self::MAX_TABLE_GUESTS {
+            return Err(SeatingError::TooManyGuests);
+        }
+        if capacities.iter().sum::<usize>() < n {
+            return Err(SeatingError::NotEnoughSeats);
+        }
+        let circles = best_circle_per_subset(&self.happiness_weights(), &self.together());
+
+        let full = (1usize << n) - 1;
+        // best[mask]: the best total for seating the people in `mask` at the tables so far
+        let mut best = vec![None; 1 << n];
+        best[0] = Some(0);
+        let mut choices = Vec::new();
+        for capacity in capacities {
+            let mut next: Vec<Option<isize>> = vec![None; 1 << n];
+            let mut choice = vec![0; 1 << n];
+            for (mask, current) in best.iter().enumerate() {
+                let Some(current) = current else {
+                    continue;
+                };
+                let free = full & !mask;
+                let mut table = free;
+                loop {
+                    if table.count_ones() as usize <= *capacity {
+                        if let Some(value) = circles[table] {
+                            let idx = mask | table;
+                            if next[idx].is_none_or(|total| total < current + value) {
+                                next[idx] = Some(current + value);
+                                choice[idx] = table;
+                            }
+                        }
+                    }
+                    if table == 0 {
+                        break;
+                    }
+                    table = (table - 1) & free;
+                }
+            }
+            best = next;
+            choices.push(choice);
+        }
+        best[full].ok_or(SeatingError::Infeasible)?;
+
+        let mut mask = full;
+        let mut members = Vec::new();
+        for choice in choices.iter().rev() {
+            members.push(choice[mask]);
+            mask &= !choice[mask];
+        }
+        let tables = members
+            .into_iter()
+            .rev()
+            .map(|table| self.table(table))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SeatingError::Infeasible)?;
+        Ok(Tables {
+            total: tables.iter().map(|table| table.total).sum(),
+            tables,
+        })
     }
 
-    fn add_self(&mut self) {
+    /// The best arrangement for the people in `members` at a table of their own.
+    fn table(&self, members: usize) -> Option<Arrangement<'a>> {
+        let persons = (0..self.persons.len())
+            .filter(|p| members & (1 << p) != 0)
+            .map(|p| self.persons[p])
+            .collect::<Vec<_>>();
+        if persons.is_empty() {
+            return Some(Arrangement {
+                seats: Vec::new(),
+                total: 0,
+            });
+        }
+        let mut constraints = Vec::new();
+        for constraint in &self.constraints {
+            match constraint {
+                Constraint::NextTo(person1, person2) | Constraint::NotNextTo(person1, person2) => {
+                    match (persons.contains(person1), persons.contains(person2)) {
+                        (true, true) => constraints.push(*constraint),
+                        (true, false) | (false, true)
+                            if matches!(constraint, Constraint::NextTo(_, _)) =>
+                        {
+                            return None
+                        }
+                        _ => {}
+                    }
+                }
+                Constraint::Seat(_, _) => {}
+            }
+        }
+        let table = Happiness {
+            persons,
+            happiness: self.happiness.clone(),
+            constraints,
+        };
+        let order = table.find_best_seating().ok()?;
+        Some(table.arrangement(&order))
+    }
+
+    /// Adds a guest, with `preference(person)` giving how happy the guest is next to each
+    /// person already there and how happy that person is next to the guest.
+    fn add_guest<F>(&mut self, guest: &'a str, preference: F) -> Result<(), ()>
+    where
+        F: Fn(&'a str) -> (isize, isize),
+    {
+        if self.persons.contains(&guest) {
+            return Err(());
+        }
         for person in &self.persons {
-            self.happiness.insert((Self::SELF, person), 0);
-            self.happiness.insert((person, Self::SELF), 0);
+            let (toward, from) = preference(person);
+            self.happiness.insert((guest, person), toward);
+            self.happiness.insert((person, guest), from);
         }
-        self.persons.push(Self::SELF);
+        self.persons.push(guest);
+        Ok(())
+    }
+
+    /// Removes a guest along with their preferences and the constraints mentioning them.
+    fn remove_guest(&mut self, guest: &str) -> Result<(), ()> {
+        let idx = self.persons.iter().position(|p| *p == guest).ok_or(())?;
+        self.persons.remove(idx);
+        self.happiness
+            .retain(|(person1, person2), _| *person1 != guest && *person2 != guest);
+        self.constraints.retain(|constraint| match constraint {
+            Constraint::NextTo(person1, person2) | Constraint::NotNextTo(person1, person2) => {
+                *person1 != guest && *person2 != guest
+            }
+            Constraint::Seat(person, _) => *person != guest,
+        });
+        Ok(())
     }
 }
 
-fn permutations<T, F>(elements: Vec<T>, callback: F) -> Vec<T>
-where
-    F: FnMut(&Vec<T>),
-{
-    gen_permutations(elements.len(), elements, callback).0
+/// Held-Karp over circular seatings with `start` in seat 0. `weights` are `None` for people
+/// that can't sit next to each other and `seats` are the seats people have to take, if any.
+///
+/// Returns the best total and up to `limit` seatings reaching it, if there are any. With
+/// `skip_mirrors` only the seatings where the person in seat 1 has a lower index than the one
+/// in the last seat are kept.
+fn best_circles(
+    weights: &[Vec<Option<isize>>],
+    seats: &[Option<usize>],
+    start: usize,
+    skip_mirrors: bool,
+    limit: usize,
+) -> Option<(isize, Vec<Vec<usize>>)> {
+    let n = weights.len();
+    let reserved = (0..n)
+        .map(|seat| seats.contains(&Some(seat)))
+        .collect::<Vec<_>>();
+    let can_sit = |person: usize, seat: usize| match seats[person] {
+        Some(fixed) => fixed == seat,
+        None => !reserved[seat],
+    };
+    if !can_sit(start, 0) {
+        return None;
+    }
+    if n == 1 {
+        return Some((0, vec![vec![start]]));
+    }
+
+    // everyone but the first person, who always sits first
+    let others = (0..n).filter(|p| *p != start).collect::<Vec<_>>();
+    let m = others.len();
+    // best[mask * m + last]: seating the people in `mask` in a row after the first person,
+    // ending with `last`
+    let mut best = vec![isize::MIN; (1 << m) * m];
+    for (last, person) in others.iter().enumerate() {
+        if let (true, Some(weight)) = (can_sit(*person, 1), weights[start][*person]) {
+            best[(1 << last) * m + last] = weight;
+        }
+    }
+    for mask in 1..(1usize << m) {
+        let seat = mask.count_ones() as usize + 1;
+        for last in 0..m {
+            let current = best[mask * m + last];
+            if current == isize::MIN {
+                continue;
+            }
+            let row = &weights[others[last]];
+            for (next, person) in others.iter().enumerate() {
+                if mask & (1 << next) == 0 && can_sit(*person, seat) {
+                    if let Some(weight) = row[*person] {
+                        let idx = (mask | (1 << next)) * m + next;
+                        best[idx] = best[idx].max(current + weight);
+                    }
+                }
+            }
+        }
+    }
+
+    // walk back from the best ways to close the circle, from the highest last person down so
+    // that the first walks find the most seatings that aren't mirror images
+    let full = (1 << m) - 1;
+    let closing = |last: usize| {
+        let row = best[full * m + last];
+        weights[others[last]][start]
+            .filter(|_| row != isize::MIN)
+            .map(|weight| row + weight)
+    };
+    let top = (0..m).filter_map(closing).max()?;
+    let mut seatings = Vec::new();
+    for last in (0..m).rev().filter(|last| closing(*last) == Some(top)) {
+        // `others` is sorted so lower indices there are lower person indices
+        let first_below = if skip_mirrors { last } else { m };
+        let mut tail = vec![last];
+        let walk = Walk {
+            best: &best,
+            weights,
+            others: &others,
+            first_below,
+            limit,
+        };
+        walk.back(full, &mut tail, &mut seatings);
+    }
+    let seatings = seatings
+        .into_iter()
+        .map(|seating| {
+            let mut order = vec![start];
+            order.extend(seating);
+            order
+        })
+        .collect();
+    Some((top, seatings))
 }
 
-fn gen_permutations<T, F>(k: usize, mut elements: Vec<T>, mut callback: F) -> (Vec<T>, F)
-where
-    F: FnMut(&Vec<T>),
-{
-    if k == 1 {
-        callback(&elements);
-    } else {
-        (elements, callback) = gen_permutations(k - 1, elements, callback);
-        for i in 0..k - 1 {
-            elements.swap(if k % 2 == 0 { i } else { 0 }, k - 1);
-            (elements, callback) = gen_permutations(k - 1, elements, callback);
+/// The best circular seating total for every set of people (by bit mask) that seats all the
+/// pairs in it that are `together` next to each other, if there is one. Sets with someone whose
+/// partner isn't in the set have none. Each circle starts with its lowest numbered person.
+fn best_circle_per_subset(
+    weights: &[Vec<Option<isize>>],
+    together: &[Vec<bool>],
+) -> Vec<Option<isize>> {
+    let n = weights.len();
+    let joined = |p1: usize, p2: usize| weights[p1][p2].map(|w| (usize::from(together[p1][p2]), w));
+    // paths[mask * n + last]: from the lowest person in `mask` through all of `mask` to `last`,
+    // as the number of pairs that are together and the happiness, the first counting most
+    let mut paths = vec![None; (1 << n) * n];
+    for p in 0..n {
+        paths[(1 << p) * n + p] = Some((0, 0));
+    }
+    let mut circles = vec![None; 1 << n];
+    circles[0] = Some((0, 0));
+    for mask in 1..(1usize << n) {
+        let start = mask.trailing_zeros() as usize;
+        for last in 0..n {
+            let Some((pairs, happiness)) = paths[mask * n + last] else {
+                continue;
+            };
+            let circle = match mask.count_ones() {
+                1 => Some((0, 0)),
+                // two people are neighbours on both sides, but that's still one pair
+                2 => weights[last][start].map(|w| (pairs, happiness + w)),
+                _ => joined(last, start).map(|(p, w)| (pairs + p, happiness + w)),
+            };
+            circles[mask] = circles[mask].max(circle);
+            for next in (start + 1..n).filter(|next| mask & (1 << next) == 0) {
+                if let Some((p, w)) = joined(last, next) {
+                    let idx = (mask | (1 << next)) * n + next;
+                    paths[idx] = paths[idx].max(Some((pairs + p, happiness + w)));
+                }
+            }
+        }
+    }
+
+    let pairs = (0..n)
+        .flat_map(|p1| (p1 + 1..n).map(move |p2| (p1, p2)))
+        .filter(|(p1, p2)| together[*p1][*p2])
+        .collect::<Vec<_>>();
+    (0..1usize << n)
+        .map(|mask| {
+            let seated = |p: &usize| mask & (1 << p) != 0;
+            let mut inside = 0;
+            for (p1, p2) in &pairs {
+                match (seated(p1), seated(p2)) {
+                    (true, true) => inside += 1,
+                    (false, false) => {}
+                    _ => return None,
+                }
+            }
+            circles[mask]
+                .filter(|(joined, _)| *joined == inside)
+                .map(|(_, happiness)| happiness)
+        })
+        .collect()
+}
+
+/// Walking back through the Held-Karp table of [`best_circles`] to collect the best rows.
+struct Walk<'w> {
+    best: &'w [isize],
+    weights: &'w [Vec<Option<isize>>],
+    others: &'w [usize],
+    /// Rows must start with someone whose index in `others` is below this.
+    first_below: usize,
+    limit: usize,
+}
+
+impl Walk<'_> {
+    /// Collects the best rows ending with `tail` (stored backwards) for people in `mask`, until
+    /// there are `limit` of them.
+    fn back(&self, mask: usize, tail: &mut Vec<usize>, seatings: &mut Vec<Vec<usize>>) {
+        // enough rows already, or nobody left who could start this one
+        if seatings.len() >= self.limit || mask & ((1 << self.first_below) - 1) == 0 {
+            return;
+        }
+        let m = self.others.len();
+        let last = *tail.last().expect("tail should not be empty");
+        if mask == 1 << last {
+            seatings.push(tail.iter().rev().map(|idx| self.others[*idx]).collect());
+            return;
+        }
+        let current = self.best[mask * m + last];
+        let mask = mask & !(1 << last);
+        for prev in (0..m).filter(|prev| mask & (1 << prev) != 0) {
+            let prev_best = self.best[mask * m + prev];
+            if prev_best == isize::MIN {
+                continue;
+            }
+            let weight = self.weights[self.others[prev]][self.others[last]];
+            if weight.map(|weight| prev_best + weight) == Some(current) {
+                tail.push(prev);
+                self.back(mask, tail, seatings);
+                tail.pop();
+            }
         }
     }
-    (elements, callback)
+}
+
+fn print_arrangements(arrangements: &[Arrangement]) {
+    for arrangement in arrangements {
+        let seats = arrangement
+            .seats
+            .iter()
+            .map(|seat| {
+                format!(
+                    "{} ({:+}/{:+})",
+                    seat.person, seat.from_left, seat.from_right
+                )
+            })
+            .collect::<Vec<_>>();
+        println!("  {}: {}", arrangement.total, seats.join(", "));
+    }
 }
 
 fn main() {
+    // ties can make for a lot of equally good arrangements
+    const SHOWN: usize = 5;
     let mut happiness =
         Happiness::parse(include_str!("../../inputs/day13.txt")).expect("input should parse");
-    println!("Part 1: {}", happiness.find_best_happiness());
-    happiness.add_self();
-    println!("Part 2: {}", happiness.find_best_happiness());
+    println!(
+        "Part 1: {}",
+        happiness
+            .find_best_happiness()
+            .expect("there should be a seating")
+    );
+    print_arrangements(
+        &happiness
+            .find_best_arrangements(SHOWN)
+            .expect("there should be a seating"),
+    );
+    for guest in &happiness.persons {
+        let mut without = happiness.clone();
+        without.remove_guest(guest).expect("guest should be there");
+        if let Ok(best) = without.find_best_happiness() {
+            println!("  without {guest}: {best}");
+        }
+    }
+    happiness
+        .add_guest("You", |_| (0, 0))
+        .expect("you should not be a guest yet");
+    println!(
+        "Part 2: {}",
+        happiness
+            .find_best_happiness()
+            .expect("there should be a seating")
+    );
+    print_arrangements(
+        &happiness
+            .find_best_arrangements(SHOWN)
+            .expect("there should be a seating"),
+    );
+    let tables = happiness
+        .find_best_tables(&[5, 4])
+        .expect("there should be enough seats");
+    println!("At tables for 5 and 4: {}", tables.total);
+    print_arrangements(&tables.tables);
+}
+
+#[cfg(test)]
+mod tests {
+    use advent_of_code_2015::rng::Rng;
+
+    use super::*;
+
+    const SAMPLE: &str = "Alice would gain 54 happiness units by sitting next to Bob.
+Alice would lose 79 happiness units by sitting next to Carol.
+Alice would lose 2 happiness units by sitting next to David.
+Bob would gain 83 happiness units by sitting next to Alice.
+Bob would lose 7 happiness units by sitting next to Carol.
+Bob would lose 63 happiness units by sitting next to David.
+Carol would lose 62 happiness units by sitting next to Alice.
+Carol would gain 60 happiness units by sitting next to Bob.
+Carol would gain 55 happiness units by sitting next to David.
+David would gain 46 happiness units by sitting next to Alice.
+David would lose 7 happiness units by sitting next to Bob.
+David would gain 41 happiness units by sitting next to Carol.";
+
+    fn brute_force_best_happiness(happiness: &Happiness) -> Result<isize, SeatingError> {
+        let mut best = None;
+        permutations(happiness.persons.clone(), |perm| {
+            if happiness.satisfies_constraints(perm) {
+                let candidate = happiness.calculate_happiness(perm);
+                best = best.max(Some(candidate));
+            }
+        });
+        best.ok_or(SeatingError::Infeasible)
+    }
+
+    /// Tries every way of putting people at tables, and every order at each table.
+    /// Tries every way of putting people at tables, and every order at each table that meets
+    /// the constraints between the people there. Fixed seats are ignored.
+    fn brute_force_best_tables(happiness: &Happiness, capacities: &[usize]) -> Option<isize> {
+        let n = happiness.persons.len();
+        let k = capacities.len();
+        let mut best = None;
+        for assignment in 0..k.pow(n as u32) {
+            let mut tables = vec![Vec::new(); k];
+            let mut rest = assignment;
+            for person in &happiness.persons {
+                tables[rest % k].push(*person);
+                rest /= k;
+            }
+            if tables.iter().zip(capacities).any(|(t, c)| t.len() > *c) {
+                continue;
+            }
+            let mut total = Some(0);
+            for table in tables.into_iter().filter(|t| !t.is_empty()) {
+                let constraints = happiness
+                    .constraints
+                    .iter()
+                    .filter(|constraint| match constraint {
+                        Constraint::NextTo(person1, person2)
+                        | Constraint::NotNextTo(person1, person2) => {
+                            table.contains(person1) || table.contains(person2)
+                        }
+                        Constraint::Seat(_, _) => false,
+                    })
+                    .cloned()
+                    .collect();
+                let at_table = Happiness {
+                    persons: table.clone(),
+                    happiness: happiness.happiness.clone(),
+                    constraints,
+                };
+                let mut table_best = None;
+                permutations(table, |perm| {
+                    if at_table.satisfies_constraints(perm) {
+                        table_best = table_best.max(Some(happiness.calculate_happiness(perm)));
+                    }
+                });
+                total = total.zip(table_best).map(|(total, best)| total + best);
+            }
+            best = best.max(total);
+        }
+        best
+    }
+
+    fn permutations<T, F>(elements: Vec<T>, callback: F) -> Vec<T>
+    where
+        F: FnMut(&Vec<T>),
+    {
+        gen_permutations(elements.len(), elements, callback).0
+    }
+
+    fn gen_permutations<T, F>(k: usize, mut elements: Vec<T>, mut callback: F) -> (Vec<T>, F)
+    where
+        F: FnMut(&Vec<T>),
+    {
+        if k == 1 {
+            callback(&elements);
+        } else {
+            (elements, callback) = gen_permutations(k - 1, elements, callback);
+            for i in 0..k - 1 {
+                elements.swap(if k.is_multiple_of(2) { i } else { 0 }, k - 1);
+                (elements, callback) = gen_permutations(k - 1, elements, callback);
+            }
+        }
+        (elements, callback)
+    }
+
+    /// Everyone with everyone else, with made up but repeatable values.
+    fn generated_input(persons: usize, seed: usize) -> String {
+        let mut input = String::new();
+        let mut rng = Rng::new(seed as u64);
+        for p1 in 0..persons {
+            for p2 in 0..persons {
+                if p1 != p2 {
+                    let delta = rng.below(201);
+                    let (verb, amount) = if delta < 100 {
+                        ("lose", 100 - delta)
+                    } else {
+                        ("gain", delta - 100)
+                    };
+                    input += &format!(
+                        "P{p1} would {verb} {amount} happiness units by sitting next to P{p2}.\n"
+                    );
+                }
+            }
+        }
+        input
+    }
+
+    #[test]
+    fn test_sample() {
+        let happiness = Happiness::parse(SAMPLE).unwrap();
+        assert_eq!(
+            happiness.calculate_happiness(&["David", "Alice", "Bob", "Carol"]),
+            330
+        );
+        assert_eq!(happiness.find_best_happiness(), Ok(330));
+        assert_eq!(brute_force_best_happiness(&happiness), Ok(330));
+    }
+
+    #[test]
+    fn test_sample_arrangement() {
+        let happiness = Happiness::parse(SAMPLE).unwrap();
+        let arrangements = happiness.find_best_arrangements(10).unwrap();
+        assert_eq!(arrangements.len(), 1);
+        let seat = |person, from_left, from_right| Seat {
+            person,
+            from_left,
+            from_right,
+        };
+        assert_eq!(
+            arrangements[0],
+            Arrangement {
+                seats: vec![
+                    seat("Alice", -2, 54),
+                    seat("Bob", 83, -7),
+                    seat("Carol", 60, 55),
+                    seat("David", 41, 46),
+                ],
+                total: 330,
+            }
+        );
+    }
+
+    #[test]
+    fn test_alternatives() {
+        // everyone likes everyone the same, so every seating is as good as any other
+        let input = "A would gain 1 happiness units by sitting next to B.
+A would gain 1 happiness units by sitting next to C.
+A would gain 1 happiness units by sitting next to D.
+B would gain 1 happiness units by sitting next to C.
+B would gain 1 happiness units by sitting next to D.
+C would gain 1 happiness units by sitting next to D.";
+        let happiness = Happiness::parse(input).unwrap();
+        let arrangements = happiness.find_best_arrangements(usize::MAX).unwrap();
+        let mut orders = arrangements
+            .iter()
+            .map(|a| a.seats.iter().map(|s| s.person).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        orders.sort();
+        assert_eq!(
+            orders,
+            vec![
+                vec!["A", "B", "C", "D"],
+                vec!["A", "B", "D", "C"],
+                vec!["A", "C", "B", "D"],
+            ]
+        );
+        assert!(arrangements.iter().all(|a| a.total == 4));
+        assert_eq!(happiness.find_best_arrangements(2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        for persons in 2..=7 {
+            let input = generated_input(persons, persons);
+            let mut happiness = Happiness::parse(&input).unwrap();
+            assert_eq!(
+                happiness.find_best_happiness(),
+                brute_force_best_happiness(&happiness),
+                "{persons}"
+            );
+            for arrangement in happiness.find_best_arrangements(usize::MAX).unwrap() {
+                let order = arrangement
+                    .seats
+                    .iter()
+                    .map(|s| s.person)
+                    .collect::<Vec<_>>();
+                assert_eq!(arrangement.total, happiness.calculate_happiness(&order));
+                assert_eq!(
+                    arrangement.total,
+                    arrangement
+                        .seats
+                        .iter()
+                        .map(|s| s.from_left + s.from_right)
+                        .sum::<isize>()
+                );
+            }
+            happiness.add_guest("You", |_| (0, 0)).unwrap();
+            assert_eq!(
+                happiness.find_best_happiness(),
+                brute_force_best_happiness(&happiness),
+                "{persons} and you"
+            );
+        }
+    }
+
+    #[test]
+    fn test_many_guests() {
+        for persons in [17, 20] {
+            let input = generated_input(persons, 2015);
+            let happiness = Happiness::parse(&input).unwrap();
+            let seating = happiness.find_best_seating().unwrap();
+            assert_eq!(seating.len(), persons);
+            assert_eq!(seating[0], happiness.persons[0]);
+            let best = happiness.calculate_happiness(&seating);
+            assert!(best >= happiness.calculate_happiness(&happiness.persons));
+        }
+        let input = generated_input(21, 2015);
+        let happiness = Happiness::parse(&input).unwrap();
+        assert_eq!(
+            happiness.find_best_happiness(),
+            Err(SeatingError::TooManyGuests)
+        );
+    }
+
+    #[test]
+    fn test_many_tied_guests() {
+        // every one of the 11!/2 seatings is among the best
+        let mut happiness = Happiness::parse("").unwrap();
+        let names = (0..12).map(|p| format!("P{p}")).collect::<Vec<_>>();
+        for name in &names {
+            happiness.add_guest(name, |_| (1, 1)).unwrap();
+        }
+        assert_eq!(happiness.find_best_happiness(), Ok(2 * 12));
+        let arrangements = happiness.find_best_arrangements(10).unwrap();
+        assert_eq!(arrangements.len(), 10);
+        let mut orders = arrangements
+            .iter()
+            .map(|a| a.seats.iter().map(|s| s.person).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        // no mirror images
+        for order in &mut orders {
+            if order[1] > order[11] {
+                order[1..].reverse();
+            }
+        }
+        orders.sort();
+        orders.dedup();
+        assert_eq!(orders.len(), 10);
+        assert!(happiness.find_best_tables(&[6, 6]).is_ok());
+    }
+
+    #[test]
+    fn test_sample_constraints() {
+        let with = |constraints: &str| SAMPLE.to_owned() + "\n" + constraints;
+
+        let input = with("Alice must sit next to Carol.");
+        let happiness = Happiness::parse(&input).unwrap();
+        let seating = happiness.find_best_seating().unwrap();
+        assert!(happiness.satisfies_constraints(&seating));
+        assert_eq!(happiness.find_best_happiness(), Ok(22));
+
+        let input = with("Alice must not sit next to David.");
+        let happiness = Happiness::parse(&input).unwrap();
+        assert_eq!(happiness.find_best_happiness(), Ok(22));
+
+        let input = with("Carol sits at seat 2.\nDavid sits at seat 0.");
+        let happiness = Happiness::parse(&input).unwrap();
+        let seating = happiness.find_best_seating().unwrap();
+        assert_eq!((seating[0], seating[2]), ("David", "Carol"));
+        assert_eq!(happiness.find_best_happiness(), Ok(-114));
+        assert_eq!(brute_force_best_happiness(&happiness), Ok(-114));
+    }
+
+    #[test]
+    fn test_impossible_constraints() {
+        for constraints in [
+            // only two neighbours each
+            "Alice must sit next to Bob.
+Alice must sit next to Carol.
+Alice must sit next to David.",
+            "Alice must sit next to Bob.\nAlice must not sit next to Bob.",
+            "Alice sits at seat 0.\nBob sits at seat 0.",
+            "Alice sits at seat 0.\nAlice sits at seat 1.",
+            "Alice sits at seat 4.",
+        ] {
+            let input = SAMPLE.to_owned() + "\n" + constraints;
+            let happiness = Happiness::parse(&input).unwrap();
+            assert_eq!(
+                happiness.find_best_happiness(),
+                Err(SeatingError::Infeasible),
+                "{constraints}"
+            );
+            assert_eq!(
+                happiness.find_best_arrangements(1),
+                Err(SeatingError::Infeasible)
+            );
+            assert_eq!(
+                brute_force_best_happiness(&happiness),
+                Err(SeatingError::Infeasible)
+            );
+        }
+        assert_eq!(
+            Happiness::parse("Alice must sit next to Bob")
+                .unwrap()
+                .constraints,
+            vec![Constraint::NextTo("Alice", "Bob")]
+        );
+        assert!(Happiness::parse("Alice sits at seat first.").is_err());
+    }
+
+    #[test]
+    fn test_constraints_against_brute_force() {
+        let mut rng = Rng::new(7);
+        for round in 0..40 {
+            let persons = 3 + round % 5;
+            let mut input = generated_input(persons, round);
+            for _ in 0..1 + rng.below(3) {
+                let (p1, p2) = (rng.below(persons), rng.below(persons));
+                input += &match rng.below(3) {
+                    0 if p1 != p2 => format!("P{p1} must sit next to P{p2}.\n"),
+                    1 if p1 != p2 => format!("P{p1} must not sit next to P{p2}.\n"),
+                    _ => format!("P{p1} sits at seat {}.\n", rng.below(persons)),
+                };
+            }
+            let happiness = Happiness::parse(&input).unwrap();
+            let best = brute_force_best_happiness(&happiness);
+            assert_eq!(happiness.find_best_happiness(), best, "{input}");
+            for seating in happiness.find_best_seatings(usize::MAX).unwrap_or_default() {
+                assert!(happiness.satisfies_constraints(&seating));
+                assert_eq!(Ok(happiness.calculate_happiness(&seating)), best);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_tables() {
+        let happiness = Happiness::parse(SAMPLE).unwrap();
+        assert_eq!(happiness.find_best_tables(&[4]).unwrap().total, 330);
+        assert_eq!(happiness.find_best_tables(&[6, 1]).unwrap().total, 330);
+        // Alice with Bob, Carol with David
+        let tables = happiness.find_best_tables(&[2, 2]).unwrap();
+        assert_eq!(tables.total, 2 * (54 + 83) + 2 * (55 + 41));
+        let members = tables
+            .tables
+            .iter()
+            .map(|t| t.seats.iter().map(|s| s.person).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec![vec!["Alice", "Bob"], vec!["Carol", "David"]]);
+        assert_eq!(
+            happiness.find_best_tables(&[3]),
+            Err(SeatingError::NotEnoughSeats)
+        );
+        assert_eq!(
+            happiness.find_best_tables(&[1, 1, 1]),
+            Err(SeatingError::NotEnoughSeats)
+        );
+
+        let input = SAMPLE.to_owned() + "\nAlice must not sit next to Bob.";
+        let happiness = Happiness::parse(&input).unwrap();
+        let tables = happiness.find_best_tables(&[2, 2]).unwrap();
+        assert_eq!(tables.total, 2 * (-2 + 46) + 2 * (-7 + 60));
+        let input = SAMPLE.to_owned() + "\nAlice must sit next to Carol.";
+        let happiness = Happiness::parse(&input).unwrap();
+        let tables = happiness.find_best_tables(&[3, 1]).unwrap();
+        assert!(tables.tables[0].seats.iter().any(|s| s.person == "Carol"));
+        assert!(tables.tables[0].seats.iter().any(|s| s.person == "Alice"));
+        let input = SAMPLE.to_owned() + "\nAlice must not sit next to Bob.";
+        let happiness = Happiness::parse(&input).unwrap();
+        assert_eq!(
+            happiness.find_best_tables(&[2, 1, 1]).map(|t| t.total),
+            Ok(2 * (55 + 41))
+        );
+        let input =
+            SAMPLE.to_owned() + "\nAlice must sit next to Bob.\nAlice must sit next to Carol.";
+        let happiness = Happiness::parse(&input).unwrap();
+        assert_eq!(
+            happiness.find_best_tables(&[2, 2]),
+            Err(SeatingError::Infeasible)
+        );
+        // Alice with both of them, even though Alice and Bob alone would look better
+        let tables = happiness.find_best_tables(&[3, 2]).unwrap();
+        let members = tables
+            .tables
+            .iter()
+            .map(|t| t.seats.iter().map(|s| s.person).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec![vec!["Alice", "Bob", "Carol"], vec!["David"]]);
+        assert_eq!(
+            Some(tables.total),
+            brute_force_best_tables(&happiness, &[3, 2])
+        );
+
+        let input = generated_input(17, 17);
+        let happiness = Happiness::parse(&input).unwrap();
+        assert_eq!(
+            happiness.find_best_tables(&[9, 8]),
+            Err(SeatingError::TooManyGuests)
+        );
+    }
+
+    #[test]
+    fn test_tables_against_brute_force() {
+        for (persons, capacities) in [
+            (3, vec![2, 2]),
+            (4, vec![3, 3]),
+            (5, vec![2, 2, 2]),
+            (6, vec![4, 3]),
+            (6, vec![2, 2, 2]),
+            (7, vec![3, 5]),
+        ] {
+            let input = generated_input(persons, persons + capacities.len());
+            let happiness = Happiness::parse(&input).unwrap();
+            let tables = happiness.find_best_tables(&capacities).unwrap();
+            assert_eq!(
+                Some(tables.total),
+                brute_force_best_tables(&happiness, &capacities),
+                "{persons} at {capacities:?}"
+            );
+            assert_eq!(tables.tables.len(), capacities.len());
+            for (table, capacity) in tables.tables.iter().zip(&capacities) {
+                assert!(table.seats.len() <= *capacity);
+            }
+        }
+
+        let mut rng = Rng::new(40);
+        for round in 0..40 {
+            let persons = 3 + round % 4;
+            let capacities = (0..2 + rng.below(2))
+                .map(|_| 1 + rng.below(persons))
+                .collect::<Vec<_>>();
+            let mut input = generated_input(persons, round);
+            for _ in 0..1 + rng.below(3) {
+                let (p1, p2) = (rng.below(persons), rng.below(persons));
+                if p1 != p2 {
+                    let not = if rng.below(2) == 0 { "" } else { "not " };
+                    input += &format!("P{p1} must {not}sit next to P{p2}.\n");
+                }
+            }
+            let happiness = Happiness::parse(&input).unwrap();
+            let tables = happiness.find_best_tables(&capacities);
+            assert_eq!(
+                tables.as_ref().ok().map(|tables| tables.total),
+                brute_force_best_tables(&happiness, &capacities),
+                "{input} at {capacities:?}"
+            );
+            for table in tables.iter().flat_map(|tables| &tables.tables) {
+                let order = table.seats.iter().map(|s| s.person).collect::<Vec<_>>();
+                if !order.is_empty() {
+                    assert_eq!(table.total, happiness.calculate_happiness(&order));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_guests() {
+        let mut happiness = Happiness::parse(SAMPLE).unwrap();
+        // everyone loves Eve, and she loves Bob
+        happiness
+            .add_guest(
+                "Eve",
+                |person| if person == "Bob" { (100, 50) } else { (0, 50) },
+            )
+            .unwrap();
+        assert_eq!(happiness.add_guest("Eve", |_| (0, 0)), Err(()));
+        assert_eq!(happiness.get_happiness("Eve", "Bob"), 150);
+        assert_eq!(happiness.get_happiness("Alice", "Eve"), 50);
+        let best = happiness.find_best_happiness();
+        assert_eq!(best, brute_force_best_happiness(&happiness));
+        assert!(best.unwrap() > 330);
+        let seating = happiness.find_best_seating().unwrap();
+        let eve = seating.iter().position(|p| *p == "Eve").unwrap();
+        assert!(seating[(eve + 1) % 5] == "Bob" || seating[(eve + 4) % 5] == "Bob");
+
+        happiness.remove_guest("Eve").unwrap();
+        assert_eq!(happiness.remove_guest("Eve"), Err(()));
+        assert_eq!(happiness.find_best_happiness(), Ok(330));
+
+        let input = SAMPLE.to_owned() + "\nAlice must sit next to Carol.\nDavid sits at seat 1.";
+        let mut happiness = Happiness::parse(&input).unwrap();
+        happiness.remove_guest("Carol").unwrap();
+        assert_eq!(happiness.persons, vec!["Alice", "Bob", "David"]);
+        assert_eq!(happiness.constraints, vec![Constraint::Seat("David", 1)]);
+        assert!(happiness
+            .happiness
+            .keys()
+            .all(|(person1, person2)| *person1 != "Carol" && *person2 != "Carol"));
+        assert_eq!(
+            happiness.find_best_happiness(),
+            brute_force_best_happiness(&happiness)
+        );
+    }
 }
@@ -1,7 +1,9 @@
+use std::cmp::Ordering;
 use std::str::FromStr;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 struct Reindeer {
+    name: String,
     speed: usize,
     run_time: usize,
     rest_time: usize,
@@ -14,6 +16,7 @@ impl FromStr for Reindeer {
         // 0     1   2   3  4    5   6 7        8   9    10   11   12  13  14
         // Vixen can fly 19 km/s for 7 seconds, but then must rest for 124 seconds.
         let parts = s.split_whitespace().collect::<Vec<_>>();
+        let name = parts.first().ok_or(())?.to_string();
         let parse_usize = |idx: usize| -> Result<usize, ()> {
             parts.get(idx).ok_or(())?.parse::<usize>().map_err(|_| ())
         };
@@ -21,6 +24,7 @@ impl FromStr for Reindeer {
         let run_time = parse_usize(6)?;
         let rest_time = parse_usize(13)?;
         Ok(Self {
+            name,
             speed,
             run_time,
             rest_time,
@@ -35,23 +39,419 @@ impl Reindeer {
         (runs * self.run_time + [seconds_left, self.run_time].into_iter().min().unwrap())
             * self.speed
     }
+
+    fn cycle(&self) -> usize {
+        self.run_time + self.rest_time
+    }
+
+    /// Compares km per cycle / cycle length without dividing.
+    fn cmp_average_speed(&self, other: &Self) -> Ordering {
+        (self.speed * self.run_time * other.cycle())
+            .cmp(&(other.speed * other.run_time * self.cycle()))
+    }
+
+    /// How fast the reindeer flies from the end of `second` on, and until which second.
+    fn pace(&self, second: usize) -> (usize, usize) {
+        let phase = second % self.cycle();
+        if phase < self.run_time {
+            (self.speed, second + self.run_time - phase)
+        } else {
+            (0, second + self.cycle() - phase)
+        }
+    }
+
+    /// Whether the reindeer flies during the given second (counting from 1).
+    fn is_flying_during(&self, second: usize) -> bool {
+        (second - 1) % (self.run_time + self.rest_time) < self.run_time
+    }
 }
 
-fn calculate_points(reindeers: &[Reindeer], time: usize) -> Vec<usize> {
-    let mut points = vec![0; reindeers.len()];
-    for t in 1..=time {
-        let distances = reindeers
+/// One point per second in the lead (ties included), jumping from one event to the next with
+/// [`Race`]. Once the reindeers that are fastest on average can't be caught any more, the race
+/// between them repeats with a period of all their cycle lengths, so only one period and what
+/// is left after the last whole one need to be raced.
+fn calculate_points_by_events(reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+    let mut race = Race::new(reindeers);
+    let front = front_group(reindeers);
+    while race.second < time && !stays_ahead(reindeers, &front, race.second + 1) {
+        race.next_stretch(time);
+    }
+    let period = front
+        .iter()
+        .try_fold(1, |period, idx| lcm(period, reindeers[*idx].cycle()));
+    race.racing = front;
+    if let Some(period) = period.filter(|period| race.second + period <= time) {
+        // everyone in front flies as far in a period, so they are back where they started
+        // relative to each other
+        let before = race.points.clone();
+        race.run_until(race.second + period);
+        let periods = (time - race.second) / period;
+        for (points, before) in race.points.iter_mut().zip(before) {
+            *points += (*points - before) * periods;
+        }
+        race.second += periods * period;
+    }
+    race.run_until(time);
+    race.points
+}
+
+/// The race going from one event to the next: a reindeer starting or stopping to fly, or
+/// someone catching up with or getting away from the leaders or the runners-up. In between
+/// everyone moves at a constant speed, so the front places stay the same and the leaders get
+/// their points in bulk. Once nobody can change places any more the rest is one last stretch.
+struct Race<'a> {
+    reindeers: &'a [Reindeer],
+    /// The reindeers still raced, the others are left where they are.
+    racing: Vec<usize>,
+    second: usize,
+    positions: Vec<usize>,
+    points: Vec<usize>,
+    speeds: Vec<usize>,
+    /// In the lead during the last stretch.
+    leaders: Vec<usize>,
+    /// Right behind the leaders during the last stretch.
+    runners_up: Vec<usize>,
+}
+
+impl<'a> Race<'a> {
+    fn new(reindeers: &'a [Reindeer]) -> Self {
+        Self {
+            reindeers,
+            racing: (0..reindeers.len()).collect(),
+            second: 0,
+            positions: vec![0; reindeers.len()],
+            points: vec![0; reindeers.len()],
+            speeds: vec![0; reindeers.len()],
+            leaders: Vec::with_capacity(reindeers.len()),
+            runners_up: Vec::with_capacity(reindeers.len()),
+        }
+    }
+
+    /// Whether every pair either always flies together or has one getting away from the other
+    /// for good from the next second on.
+    fn settled(&self) -> bool {
+        let same = |r1: &Reindeer, r2: &Reindeer| {
+            (r1.speed, r1.run_time, r1.rest_time) == (r2.speed, r2.run_time, r2.rest_time)
+        };
+        self.racing.iter().enumerate().all(|(i, idx1)| {
+            self.racing[i + 1..].iter().all(|idx2| {
+                let (r1, r2) = (&self.reindeers[*idx1], &self.reindeers[*idx2]);
+                same(r1, r2)
+                    || match r1.cmp_average_speed(r2) {
+                        Ordering::Greater => outruns(r1, r2, self.second + 1),
+                        Ordering::Less => outruns(r2, r1, self.second + 1),
+                        Ordering::Equal => false,
+                    }
+            })
+        })
+    }
+
+    /// Goes on to the next event, or to `time` if that comes first, and returns the seconds
+    /// `first..=last` raced, if any.
+    fn next_stretch(&mut self, time: usize) -> Option<(usize, usize)> {
+        if self.second >= time || self.racing.is_empty() {
+            return None;
+        }
+        let settled = self.settled();
+        // how fast everyone goes from here, and until when
+        let mut last = time;
+        for idx in &self.racing {
+            let reindeer = &self.reindeers[*idx];
+            let (speed, until) = reindeer.pace(self.second);
+            self.positions[*idx] = reindeer.distance_after(self.second);
+            self.speeds[*idx] = speed;
+            if !settled {
+                last = last.min(until);
+            }
+        }
+        let next = |idx: usize| self.positions[idx] + self.speeds[idx];
+        let best = self.racing.iter().map(|idx| next(*idx)).max()?;
+        let second_best = self
+            .racing
+            .iter()
+            .map(|idx| next(*idx))
+            .filter(|position| *position != best)
+            .max();
+        self.leaders.clear();
+        self.leaders
+            .extend(self.racing.iter().filter(|idx| next(**idx) == best));
+        self.runners_up.clear();
+        self.runners_up.extend(
+            self.racing
+                .iter()
+                .filter(|idx| Some(next(**idx)) == second_best),
+        );
+        if !settled {
+            for front in self.leaders.iter().chain(&self.runners_up) {
+                for other in &self.racing {
+                    // the faster one stays behind until it's at most one second's gain back,
+                    // and then they're tied for a second or it goes straight past
+                    let (fast, slow) = if self.speeds[*front] > self.speeds[*other] {
+                        (*front, *other)
+                    } else {
+                        (*other, *front)
+                    };
+                    let gain = self.speeds[fast] - self.speeds[slow];
+                    let behind = self.positions[slow].saturating_sub(self.positions[fast]);
+                    if gain > 0 && behind >= gain {
+                        last = last.min(self.second + (behind.div_ceil(gain) - 1).max(1));
+                    }
+                }
+            }
+        }
+
+        for leader in &self.leaders {
+            self.points[*leader] += last - self.second;
+        }
+        let first = self.second + 1;
+        self.second = last;
+        Some((first, last))
+    }
+
+    fn run_until(&mut self, time: usize) {
+        while self.next_stretch(time).is_some() {}
+    }
+}
+
+/// The reindeers with the best average speed.
+fn front_group(reindeers: &[Reindeer]) -> Vec<usize> {
+    let Some(fastest) = reindeers.iter().max_by(|r1, r2| r1.cmp_average_speed(r2)) else {
+        return Vec::new();
+    };
+    (0..reindeers.len())
+        .filter(|idx| fastest.cmp_average_speed(&reindeers[*idx]).is_eq())
+        .collect()
+}
+
+/// Whether everyone in `front` is ahead of all the others at `second` and any time after
+/// that.
+fn stays_ahead(reindeers: &[Reindeer], front: &[usize], second: usize) -> bool {
+    front.iter().all(|leader| {
+        reindeers
             .iter()
-            .map(|r| r.distance_after(t))
+            .enumerate()
+            .all(|(idx, other)| front.contains(&idx) || outruns(&reindeers[*leader], other, second))
+    })
+}
+
+/// Whether `leader`, faster on average than `other`, is ahead of it at `second` and any time
+/// after that, as it flies at least all its full cycles and `other` at most one more than its.
+fn outruns(leader: &Reindeer, other: &Reindeer, second: usize) -> bool {
+    let cycle = |r: &Reindeer| r.cycle() as i128;
+    let per_cycle = |r: &Reindeer| (r.speed * r.run_time) as i128;
+    let second = second as i128;
+    // both sides multiplied by both cycle lengths; the difference only grows with time
+    (second - cycle(leader)) * per_cycle(leader) * cycle(other)
+        > (second + cycle(other)) * per_cycle(other) * cycle(leader)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Least common multiple, if it fits.
+fn lcm(a: usize, b: usize) -> Option<usize> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// The state of the race at the end of a second.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Snapshot {
+    second: usize,
+    positions: Vec<usize>,
+    flying: Vec<bool>,
+    /// Indices of the reindeers in the lead.
+    leaders: Vec<usize>,
+    points: Vec<usize>,
+}
+
+/// Goes through the race second by second, forever.
+struct Timeline<'a> {
+    reindeers: &'a [Reindeer],
+    second: usize,
+    points: Vec<usize>,
+}
+
+impl<'a> Timeline<'a> {
+    fn new(reindeers: &'a [Reindeer]) -> Self {
+        Self {
+            reindeers,
+            second: 0,
+            points: vec![0; reindeers.len()],
+        }
+    }
+}
+
+impl Iterator for Timeline<'_> {
+    type Item = Snapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.second += 1;
+        let positions = self
+            .reindeers
+            .iter()
+            .map(|r| r.distance_after(self.second))
+            .collect::<Vec<_>>();
+        let best_position = positions.iter().cloned().max()?;
+        let leaders = (0..positions.len())
+            .filter(|idx| positions[*idx] == best_position)
             .collect::<Vec<_>>();
-        let best_distance = distances.iter().cloned().max().unwrap();
-        for (p, dist) in points.iter_mut().zip(distances.into_iter()) {
-            if dist == best_distance {
-                *p += 1;
+        for leader in &leaders {
+            self.points[*leader] += 1;
+        }
+        Some(Snapshot {
+            second: self.second,
+            flying: self
+                .reindeers
+                .iter()
+                .map(|r| r.is_flying_during(self.second))
+                .collect(),
+            positions,
+            leaders,
+            points: self.points.clone(),
+        })
+    }
+}
+
+/// The seconds (up to `time`) at which the set of leaders changes, with the new leaders.
+fn lead_changes(reindeers: &[Reindeer], time: usize) -> Vec<(usize, Vec<usize>)> {
+    let mut changes: Vec<(usize, Vec<usize>)> = Vec::new();
+    for snapshot in Timeline::new(reindeers).take(time) {
+        if changes
+            .last()
+            .is_none_or(|(_, leaders)| *leaders != snapshot.leaders)
+        {
+            changes.push((snapshot.second, snapshot.leaders));
+        }
+    }
+    changes
+}
+
+/// A way to award points to the reindeers after a race.
+trait Scoring {
+    fn name(&self) -> String;
+
+    /// Points for each reindeer after racing for `time` seconds.
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize>;
+}
+
+/// One point per km flown.
+struct Distance;
+
+impl Scoring for Distance {
+    fn name(&self) -> String {
+        "Distance".to_string()
+    }
+
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        reindeers.iter().map(|r| r.distance_after(time)).collect()
+    }
+}
+
+/// One point for every second in the lead.
+struct Lead;
+
+impl Scoring for Lead {
+    fn name(&self) -> String {
+        "Lead".to_string()
+    }
+
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        calculate_points_by_events(reindeers, time)
+    }
+}
+
+/// One point for every second in second place, behind the leaders.
+struct SecondPlace;
+
+impl Scoring for SecondPlace {
+    fn name(&self) -> String {
+        "Second place".to_string()
+    }
+
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        let mut points = vec![0; reindeers.len()];
+        let mut race = Race::new(reindeers);
+        while let Some((first, last)) = race.next_stretch(time) {
+            for runner_up in &race.runners_up {
+                points[*runner_up] += last - first + 1;
+            }
+        }
+        points
+    }
+}
+
+/// One point for being in the lead at the end of each lap of `lap` seconds.
+struct LapLeader {
+    lap: usize,
+}
+
+impl LapLeader {
+    /// Laps need to last at least a second.
+    fn new(lap: usize) -> Result<Self, ()> {
+        if lap == 0 {
+            return Err(());
+        }
+        Ok(Self { lap })
+    }
+}
+
+impl Scoring for LapLeader {
+    fn name(&self) -> String {
+        format!("Lead every {} seconds", self.lap)
+    }
+
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        let mut points = vec![0; reindeers.len()];
+        let mut race = Race::new(reindeers);
+        while let Some((first, last)) = race.next_stretch(time) {
+            let laps = last / self.lap - (first - 1) / self.lap;
+            for leader in &race.leaders {
+                points[*leader] += laps;
+            }
+        }
+        points
+    }
+}
+
+/// Points for the lead like [`Lead`], and a `bonus` for the longest time spent in the lead
+/// without a break.
+struct LeadStreak {
+    bonus: usize,
+}
+
+impl Scoring for LeadStreak {
+    fn name(&self) -> String {
+        format!("Lead with {} for the longest streak", self.bonus)
+    }
+
+    fn score(&self, reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        let mut streaks = vec![0; reindeers.len()];
+        let mut longest = vec![0; reindeers.len()];
+        let mut race = Race::new(reindeers);
+        while let Some((first, last)) = race.next_stretch(time) {
+            for (idx, streak) in streaks.iter_mut().enumerate() {
+                if race.leaders.contains(&idx) {
+                    *streak += last - first + 1;
+                    longest[idx] = longest[idx].max(*streak);
+                } else {
+                    *streak = 0;
+                }
             }
         }
+        let mut points = Lead.score(reindeers, time);
+        let record = longest.iter().cloned().max().unwrap_or_default();
+        for (p, streak) in points.iter_mut().zip(longest) {
+            if record > 0 && streak == record {
+                *p += self.bonus;
+            }
+        }
+        points
     }
-    points
 }
 
 fn main() {
@@ -60,27 +460,91 @@ fn main() {
         .map(Reindeer::from_str)
         .collect::<Result<Vec<Reindeer>, ()>>()
         .expect("input should parse");
+    let names = |idxs: &[usize]| {
+        idxs.iter()
+            .map(|idx| reindeers[*idx].name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
     println!(
         "Part 1: {}",
-        reindeers
-            .iter()
-            .map(|r| r.distance_after(2503))
+        Distance
+            .score(&reindeers, 2503)
+            .into_iter()
             .max()
             .expect("there should be at least one")
     );
     println!(
         "Part 2: {}",
-        calculate_points(&reindeers, 2503)
+        Lead.score(&reindeers, 2503)
+            .into_iter()
+            .max()
+            .expect("there should be at least one")
+    );
+    let rules: [Box<dyn Scoring>; 5] = [
+        Box::new(Distance),
+        Box::new(Lead),
+        Box::new(SecondPlace),
+        Box::new(LapLeader::new(100).expect("laps should not be empty")),
+        Box::new(LeadStreak { bonus: 500 }),
+    ];
+    for rule in rules {
+        let points = rule.score(&reindeers, 2503);
+        let best = points.iter().cloned().max().unwrap_or_default();
+        let winners = (0..points.len())
+            .filter(|idx| points[*idx] == best)
+            .collect::<Vec<_>>();
+        println!("  {}: {best} for {}", rule.name(), names(&winners));
+    }
+    println!(
+        "After a billion seconds: {}",
+        calculate_points_by_events(&reindeers, 1_000_000_000)
             .into_iter()
             .max()
             .expect("there should be at least one")
     );
+    println!("Lead changes:");
+    for (second, leaders) in lead_changes(&reindeers, 2503) {
+        println!("  {second:>4}s: {}", names(&leaders));
+    }
+    if let Some(end) = Timeline::new(&reindeers).nth(2502) {
+        println!("After {} seconds:", end.second);
+        for (idx, reindeer) in reindeers.iter().enumerate() {
+            println!(
+                "  {:<8} {:>5} km, {:<7} {:>5} points",
+                reindeer.name,
+                end.positions[idx],
+                if end.flying[idx] { "flying" } else { "resting" },
+                end.points[idx]
+            );
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use advent_of_code_2015::rng::Rng;
+
     use super::*;
 
+    /// Steps through the race one second at a time.
+    fn calculate_points(reindeers: &[Reindeer], time: usize) -> Vec<usize> {
+        let mut points = vec![0; reindeers.len()];
+        for t in 1..=time {
+            let distances = reindeers
+                .iter()
+                .map(|r| r.distance_after(t))
+                .collect::<Vec<_>>();
+            let best_distance = distances.iter().cloned().max().unwrap();
+            for (p, dist) in points.iter_mut().zip(distances) {
+                if dist == best_distance {
+                    *p += 1;
+                }
+            }
+        }
+        points
+    }
+
     #[test]
     fn test_sample() {
         let comet = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds."
@@ -88,4 +552,226 @@ mod tests {
             .unwrap();
         assert_eq!(comet.distance_after(1000), 1120);
     }
+
+    const SAMPLE: &str = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
+
+    #[test]
+    fn test_sample_timeline() {
+        let reindeers = SAMPLE
+            .lines()
+            .map(Reindeer::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(reindeers[1].name, "Dancer");
+        let timeline = Timeline::new(&reindeers).take(1000).collect::<Vec<_>>();
+        assert_eq!(
+            timeline[0],
+            Snapshot {
+                second: 1,
+                positions: vec![14, 16],
+                flying: vec![true, true],
+                leaders: vec![1],
+                points: vec![0, 1],
+            }
+        );
+        assert_eq!(timeline[10].flying, vec![false, true]);
+        assert_eq!(timeline[139].points, vec![1, 139]);
+        assert_eq!(timeline[999].points, vec![312, 689]);
+        assert_eq!(timeline[999].points, calculate_points(&reindeers, 1000));
+        assert_eq!(timeline[999].positions, vec![1120, 1056]);
+        assert_eq!(
+            lead_changes(&reindeers, 1000)[..3],
+            [(1, vec![1]), (140, vec![0]), (180, vec![1])]
+        );
+    }
+
+    #[test]
+    fn test_events_against_stepping() {
+        let reindeers = SAMPLE
+            .lines()
+            .map(Reindeer::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        for time in [0, 1, 10, 11, 140, 1000, 5000] {
+            assert_eq!(
+                calculate_points_by_events(&reindeers, time),
+                calculate_points(&reindeers, time),
+                "{time}"
+            );
+        }
+        // Comet is a little faster on average and gets away for good well before that
+        let far = calculate_points_by_events(&reindeers, 1_000_000_000);
+        let stepped = calculate_points(&reindeers, 200_000);
+        assert_eq!(far[1], stepped[1]);
+        assert_eq!(far[0] - stepped[0], 1_000_000_000 - 200_000);
+
+        let mut rng = Rng::new(2015);
+        for _ in 0..50 {
+            let reindeers = (0..1 + rng.below(6))
+                .map(|idx| Reindeer {
+                    name: format!("R{idx}"),
+                    speed: 1 + rng.below(5),
+                    run_time: 1 + rng.below(8),
+                    rest_time: rng.below(10),
+                })
+                .collect::<Vec<_>>();
+            let time = rng.below(500);
+            assert_eq!(
+                calculate_points_by_events(&reindeers, time),
+                calculate_points(&reindeers, time),
+                "{reindeers:?} for {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_equal_averages() {
+        let race = |input: &str| {
+            input
+                .lines()
+                .map(Reindeer::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let twins = race(
+            "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.",
+        );
+        // the slow one catches up at the end of every rest
+        let hare = race(
+            "Hare can fly 2 km/s for 10 seconds, but then must rest for 10 seconds.
+Tortoise can fly 1 km/s for 20 seconds, but then must rest for 0 seconds.",
+        );
+        let steady = race(
+            "Left can fly 1 km/s for 1 seconds, but then must rest for 0 seconds.
+Right can fly 1 km/s for 1 seconds, but then must rest for 0 seconds.",
+        );
+        for (reindeers, expected) in [
+            (&twins, [1_000_000_000, 1_000_000_000]),
+            (&hare, [1_000_000_000, 50_000_000]),
+            (&steady, [1_000_000_000, 1_000_000_000]),
+        ] {
+            assert_eq!(
+                calculate_points_by_events(reindeers, 1_000_000_000),
+                expected
+            );
+            assert_eq!(
+                calculate_points_by_events(reindeers, 5003),
+                calculate_points(reindeers, 5003)
+            );
+        }
+
+        // a few with the same average speed, ahead of a few slower ones
+        let mut rng = Rng::new(2016);
+        for _ in 0..30 {
+            let mut reindeers = Vec::new();
+            for idx in 0..2 + rng.below(3) {
+                let run_time = 1 + rng.below(4);
+                let rest_time = rng.below(4);
+                reindeers.push(Reindeer {
+                    name: format!("R{idx}"),
+                    speed: 12 * (run_time + rest_time) / run_time,
+                    run_time,
+                    rest_time,
+                });
+            }
+            for idx in 0..rng.below(3) {
+                reindeers.push(Reindeer {
+                    name: format!("S{idx}"),
+                    speed: 1 + rng.below(12),
+                    run_time: 1 + rng.below(8),
+                    rest_time: rng.below(8),
+                });
+            }
+            let time = rng.below(3000);
+            assert_eq!(
+                calculate_points_by_events(&reindeers, time),
+                calculate_points(&reindeers, time),
+                "{reindeers:?} for {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_race_against_timeline() {
+        let mut rng = Rng::new(2017);
+        for _ in 0..50 {
+            let reindeers = (0..1 + rng.below(6))
+                .map(|idx| Reindeer {
+                    name: format!("R{idx}"),
+                    speed: 1 + rng.below(5),
+                    run_time: 1 + rng.below(8),
+                    rest_time: rng.below(10),
+                })
+                .collect::<Vec<_>>();
+            let time = rng.below(500);
+            let mut timeline = Timeline::new(&reindeers);
+            let mut second = 0;
+            let mut race = Race::new(&reindeers);
+            while let Some((first, last)) = race.next_stretch(time) {
+                assert_eq!(first, second + 1);
+                for snapshot in timeline.by_ref().take(last - second) {
+                    assert_eq!(snapshot.leaders, race.leaders, "{reindeers:?}");
+                    let mut places = snapshot.positions.clone();
+                    places.sort();
+                    places.dedup();
+                    let runners_up = (0..reindeers.len())
+                        .filter(|idx| {
+                            places.len() > 1 && snapshot.positions[*idx] == places[places.len() - 2]
+                        })
+                        .collect::<Vec<_>>();
+                    assert_eq!(runners_up, race.runners_up, "{reindeers:?}");
+                }
+                second = last;
+            }
+            assert_eq!(second, time);
+        }
+    }
+
+    #[test]
+    fn test_sample_scoring() {
+        let reindeers = SAMPLE
+            .lines()
+            .map(Reindeer::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(Distance.score(&reindeers, 1000), vec![1120, 1056]);
+        assert_eq!(Lead.score(&reindeers, 1000), vec![312, 689]);
+        // whoever isn't leading is second, except for the one second they are tied
+        let second = SecondPlace.score(&reindeers, 1000);
+        assert_eq!(second, vec![689 - 1, 312 - 1]);
+        assert_eq!(
+            LapLeader::new(1).unwrap().score(&reindeers, 1000),
+            vec![312, 689]
+        );
+        assert_eq!(
+            LapLeader::new(500).unwrap().score(&reindeers, 1000),
+            vec![2, 0]
+        );
+        assert!(LapLeader::new(0).is_err());
+        // Dancer leads from second 1 to 139
+        assert_eq!(
+            LeadStreak { bonus: 100 }.score(&reindeers, 1000),
+            vec![312, 689 + 100]
+        );
+
+        // Comet gets away for good, so after a while it's all one stretch
+        let mut race = Race::new(&reindeers);
+        let mut stretches = 0;
+        while race.next_stretch(1_000_000_000).is_some() {
+            stretches += 1;
+        }
+        assert!(stretches < 10_000);
+        let far = SecondPlace.score(&reindeers, 1_000_000_000);
+        let near = SecondPlace.score(&reindeers, 200_000);
+        assert_eq!(far[0], near[0]);
+        assert_eq!(far[1] - near[1], 1_000_000_000 - 200_000);
+        assert_eq!(
+            LapLeader::new(1000)
+                .unwrap()
+                .score(&reindeers, 1_000_000_000)[0],
+            LapLeader::new(1000).unwrap().score(&reindeers, 200_000)[0] + 1_000_000 - 200
+        );
+    }
 }
@@ -1,50 +1,673 @@
-const INGREDIENTS: [([isize; 4], isize); 4] = [
-    ([5, -1, 0, 0], 5),
-    ([-1, 3, 0, 0], 1),
-    ([0, -1, 4, 0], 6),
-    ([-1, 0, 0, 2], 8),
-];
-
-fn solve(calories: Option<isize>) -> usize {
-    let mut best = 0;
-    for i in 0isize..=100 {
-        for j in 0..=(100 - i) {
-            for k in 0..=(100 - j) {
-                let l = 100 - i - j - k;
-                if let Some(cal) = calories {
-                    let cal_value: isize = [i, j, k, l]
-                        .iter()
-                        .zip(INGREDIENTS.iter())
-                        .map(|(frac, (_props, calories))| *frac * *calories)
-                        .sum();
-                    if cal != cal_value {
+use std::ops::RangeInclusive;
+
+use advent_of_code_2015::rng::Rng;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Ingredient<'a> {
+    name: &'a str,
+    /// In the order of [`Pantry::properties`].
+    properties: Vec<isize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Constraint<'a> {
+    Calories(RangeInclusive<isize>),
+    /// Teaspoons of an ingredient.
+    Teaspoons(&'a str, RangeInclusive<usize>),
+    /// The least total of a property.
+    AtLeast(&'a str, isize),
+}
+
+/// A mix of ingredients, and what's in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Recipe<'a> {
+    teaspoons: Vec<(&'a str, usize)>,
+    totals: Vec<(&'a str, isize)>,
+    score: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Pantry<'a> {
+    properties: Vec<&'a str>,
+    ingredients: Vec<Ingredient<'a>>,
+}
+
+impl<'a> Pantry<'a> {
+    /// Calories don't count for the score.
+    const CALORIES: &'a str = "calories";
+    const TEASPOONS: usize = 100;
+
+    fn parse(s: &'a str) -> Result<Pantry<'a>, ()> {
+        // Sprinkles: capacity 5, durability -1, flavor 0, texture 0, calories 5
+        let mut properties = Vec::new();
+        let mut ingredients = Vec::new();
+        for line in s.lines() {
+            let (name, rest) = line.split_once(": ").ok_or(())?;
+            let mut names = Vec::new();
+            let mut values = Vec::new();
+            for property in rest.split(", ") {
+                let (property, value) = property.split_once(' ').ok_or(())?;
+                names.push(property);
+                values.push(value.parse::<isize>().map_err(|_| ())?);
+            }
+            if ingredients.is_empty() {
+                properties = names;
+            } else if properties != names {
+                return Err(());
+            }
+            ingredients.push(Ingredient {
+                name,
+                properties: values,
+            });
+        }
+        Ok(Pantry {
+            properties,
+            ingredients,
+        })
+    }
+
+    /// Total amount of each property for the given teaspoons of each ingredient.
+    fn totals(&self, teaspoons: &[usize]) -> Vec<isize> {
+        (0..self.properties.len())
+            .map(|p| {
+                self.ingredients
+                    .iter()
+                    .zip(teaspoons)
+                    .map(|(ingredient, amount)| ingredient.properties[p] * *amount as isize)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The product of all the properties but calories, negative ones counting as 0. Stops at
+    /// `u128::MAX`, which takes 14 properties at 900 or so.
+    fn score(&self, teaspoons: &[usize]) -> u128 {
+        self.properties
+            .iter()
+            .zip(self.totals(teaspoons))
+            .filter(|(property, _)| **property != Self::CALORIES)
+            .map(|(_, total)| total.max(0) as u128)
+            .fold(1, u128::saturating_mul)
+    }
+
+    /// Whether the recipe meets all the constraints. Constraints on unknown ingredients or
+    /// properties can't be met.
+    fn satisfies(&self, teaspoons: &[usize], constraints: &[Constraint]) -> bool {
+        self.limits(teaspoons.iter().sum(), constraints)
+            .is_some_and(|limits| limits.violation(teaspoons, &self.totals(teaspoons)) == 0)
+    }
+
+    fn recipe(&self, teaspoons: &[usize]) -> Recipe<'a> {
+        Recipe {
+            teaspoons: self
+                .ingredients
+                .iter()
+                .map(|ingredient| ingredient.name)
+                .zip(teaspoons.iter().cloned())
+                .collect(),
+            totals: self
+                .properties
+                .iter()
+                .cloned()
+                .zip(self.totals(teaspoons))
+                .collect(),
+            score: self.score(teaspoons),
+        }
+    }
+
+    /// What the constraints allow, or `None` if they name unknown ingredients or properties.
+    fn limits(&self, teaspoons: usize, constraints: &[Constraint]) -> Option<Limits> {
+        let mut limits = Limits {
+            teaspoons: vec![0..=teaspoons; self.ingredients.len()],
+            calories: isize::MIN..=isize::MAX,
+            calorie_property: self.properties.iter().position(|p| *p == Self::CALORIES),
+            floors: vec![isize::MIN; self.properties.len()],
+        };
+        for constraint in constraints {
+            match constraint {
+                Constraint::Calories(range) => limits.calories = intersect(&limits.calories, range),
+                Constraint::Teaspoons(name, range) => {
+                    let idx = self.ingredients.iter().position(|i| i.name == *name)?;
+                    limits.teaspoons[idx] = intersect(&limits.teaspoons[idx], range);
+                }
+                Constraint::AtLeast(property, least) => {
+                    let p = self.properties.iter().position(|p| p == property)?;
+                    limits.floors[p] = limits.floors[p].max(*least);
+                }
+            }
+        }
+        Some(limits)
+    }
+
+    /// The best scoring recipe with `teaspoons` teaspoons meeting all the constraints, if any
+    /// does.
+    ///
+    /// Decides the teaspoons of one ingredient after the other, skipping the rest whenever
+    /// the teaspoons left can't make up for it. Each property can at most get the teaspoons
+    /// left times the most any ingredient left has of it, and by AM-GM the product of the
+    /// properties is at most the power of their mean (each scaled by that most, or by what it
+    /// got in the best recipe so far). Starts from a climbed recipe to have something to beat.
+    fn best_recipe(&self, teaspoons: usize, constraints: &[Constraint]) -> Option<Recipe<'a>> {
+        let limits = self.limits(teaspoons, constraints)?;
+        let k = self.ingredients.len();
+        if k == 0 {
+            return (teaspoons == 0 && self.satisfies(&[], constraints)).then(|| self.recipe(&[]));
+        }
+        let mut search = Search {
+            pantry: self,
+            limits: &limits,
+            least_after: (0..=k)
+                .map(|idx| limits.teaspoons[idx..].iter().map(|r| r.start()).sum())
+                .collect(),
+            most_after: (0..=k)
+                .map(|idx| limits.teaspoons[idx..].iter().map(|r| r.end()).sum())
+                .collect(),
+            teaspoons: vec![0; k],
+            totals: vec![0; self.properties.len()],
+            best: None,
+            best_totals: None,
+        };
+        // a good recipe to start with makes for a lot less to search
+        if let Some(recipe) = self.climb_recipe(teaspoons, constraints, 5, 0) {
+            let teaspoons = recipe.teaspoons.iter().map(|(_, amount)| *amount).collect();
+            search.best = Some((recipe.score, teaspoons));
+            search.best_totals = Some(recipe.totals.iter().map(|(_, total)| *total).collect());
+        }
+        search.branch(0, teaspoons);
+        search.best.map(|(_, teaspoons)| self.recipe(&teaspoons))
+    }
+
+    /// A good recipe found by moving one teaspoon at a time from one ingredient to another
+    /// as long as that helps, starting over from `restarts` random recipes. Recipes breaking
+    /// the constraints count as worse the more they break them, so the climb heads towards
+    /// the ones that don't.
+    fn climb_recipe(
+        &self,
+        teaspoons: usize,
+        constraints: &[Constraint],
+        restarts: usize,
+        seed: u64,
+    ) -> Option<Recipe<'a>> {
+        let limits = self.limits(teaspoons, constraints)?;
+        let k = self.ingredients.len();
+        if k == 0 {
+            return (teaspoons == 0 && self.satisfies(&[], constraints)).then(|| self.recipe(&[]));
+        }
+        let fitness = |current: &[usize]| {
+            let totals = self.totals(current);
+            let score = self.score(current);
+            // while the score is 0, better the weakest properties first
+            let mut weakest = totals
+                .iter()
+                .zip(&self.properties)
+                .filter(|(_, property)| **property != Self::CALORIES)
+                .map(|(total, _)| *total)
+                .collect::<Vec<_>>();
+            weakest.sort_unstable();
+            (-limits.violation(current, &totals), score, weakest)
+        };
+
+        let mut rng = Rng::new(seed);
+        let mut best: Option<(_, Vec<usize>)> = None;
+        for _ in 0..restarts.max(1) {
+            let mut current = vec![0; k];
+            for _ in 0..teaspoons {
+                current[rng.below(k)] += 1;
+            }
+            let mut current_fitness = fitness(&current);
+            loop {
+                let mut best_move = None;
+                for from in 0..k {
+                    if current[from] == 0 {
                         continue;
                     }
+                    for to in (0..k).filter(|to| *to != from) {
+                        current[from] -= 1;
+                        current[to] += 1;
+                        let candidate = fitness(&current);
+                        if candidate > current_fitness
+                            && best_move.as_ref().is_none_or(|(f, _, _)| candidate > *f)
+                        {
+                            best_move = Some((candidate, from, to));
+                        }
+                        current[from] += 1;
+                        current[to] -= 1;
+                    }
                 }
-                let mut properties = Vec::with_capacity(4);
-                for p in 0..4 {
-                    let prop_value: isize = [i, j, k, l]
-                        .iter()
-                        .zip(INGREDIENTS.iter())
-                        .map(|(frac, (props, _calories))| *frac * props[p])
-                        .sum();
-                    properties.push(if prop_value < 0 {
-                        0
-                    } else {
-                        prop_value as usize
-                    });
-                }
-                let score = properties.into_iter().product();
-                if best < score {
-                    best = score;
+                let Some((candidate, from, to)) = best_move else {
+                    break;
+                };
+                current[from] -= 1;
+                current[to] += 1;
+                current_fitness = candidate;
+            }
+            if best.as_ref().is_none_or(|(f, _)| current_fitness > *f) {
+                best = Some((current_fitness, current));
+            }
+        }
+        best.map(|(_, teaspoons)| teaspoons)
+            .filter(|teaspoons| self.satisfies(teaspoons, constraints))
+            .map(|teaspoons| self.recipe(&teaspoons))
+    }
+}
+
+fn intersect<T: Copy + Ord>(
+    range: &RangeInclusive<T>,
+    other: &RangeInclusive<T>,
+) -> RangeInclusive<T> {
+    *range.start().max(other.start())..=*range.end().min(other.end())
+}
+
+/// The constraints in terms of ingredient and property indices.
+struct Limits {
+    teaspoons: Vec<RangeInclusive<usize>>,
+    calories: RangeInclusive<isize>,
+    calorie_property: Option<usize>,
+    floors: Vec<isize>,
+}
+
+impl Limits {
+    /// How far the recipe is from meeting the limits, 0 if it does.
+    fn violation(&self, teaspoons: &[usize], totals: &[isize]) -> isize {
+        let outside = |value: isize, start: isize, end: isize| {
+            (start.saturating_sub(value))
+                .max(value.saturating_sub(end))
+                .max(0)
+        };
+        let teaspoons = teaspoons
+            .iter()
+            .zip(&self.teaspoons)
+            .map(|(amount, range)| {
+                outside(
+                    *amount as isize,
+                    *range.start() as isize,
+                    *range.end() as isize,
+                )
+            })
+            .sum::<isize>();
+        let calories = self.calorie_property.map(|p| totals[p]).unwrap_or_default();
+        let floors = totals
+            .iter()
+            .zip(&self.floors)
+            .map(|(total, floor)| floor.saturating_sub(*total).max(0))
+            .sum::<isize>();
+        teaspoons + outside(calories, *self.calories.start(), *self.calories.end()) + floors
+    }
+}
+
+/// The state of [`Pantry::best_recipe`].
+struct Search<'p, 'a> {
+    pantry: &'p Pantry<'a>,
+    limits: &'p Limits,
+    /// The least and most teaspoons the ingredients from an index on can take together.
+    least_after: Vec<usize>,
+    most_after: Vec<usize>,
+    teaspoons: Vec<usize>,
+    totals: Vec<isize>,
+    best: Option<(u128, Vec<usize>)>,
+    best_totals: Option<Vec<isize>>,
+}
+
+impl Search<'_, '_> {
+    fn branch(&mut self, idx: usize, left: usize) {
+        let ingredients = &self.pantry.ingredients;
+        if idx == ingredients.len() {
+            let score = self.pantry.score(&self.teaspoons);
+            if self.best.as_ref().is_none_or(|(best, _)| score > *best)
+                && self.limits.violation(&self.teaspoons, &self.totals) == 0
+            {
+                self.best = Some((score, self.teaspoons.clone()));
+                self.best_totals = Some(self.totals.clone());
+            }
+            return;
+        }
+        if !self.promising(idx, left) {
+            return;
+        }
+
+        let range = &self.limits.teaspoons[idx];
+        let least = left
+            .saturating_sub(self.most_after[idx + 1])
+            .max(*range.start());
+        let most = left
+            .saturating_sub(self.least_after[idx + 1])
+            .min(*range.end());
+        let last = idx + 1 == ingredients.len();
+        for amount in (least..=most)
+            .rev()
+            .filter(|amount| !last || *amount == left)
+        {
+            self.teaspoons[idx] = amount;
+            for (total, value) in self.totals.iter_mut().zip(&ingredients[idx].properties) {
+                *total += value * amount as isize;
+            }
+            self.branch(idx + 1, left - amount);
+            for (total, value) in self.totals.iter_mut().zip(&ingredients[idx].properties) {
+                *total -= value * amount as isize;
+            }
+        }
+        self.teaspoons[idx] = 0;
+    }
+
+    /// Whether the ingredients from `idx` on can still meet the limits and beat the best
+    /// score with the `left` teaspoons.
+    fn promising(&self, idx: usize, left: usize) -> bool {
+        let rest = &self.pantry.ingredients[idx..];
+        let left = left as isize;
+        let mut bounds = Vec::new();
+        for (p, total) in self.totals.iter().enumerate() {
+            let most = total + left * rest.iter().map(|i| i.properties[p]).max().unwrap_or(0);
+            let least = total + left * rest.iter().map(|i| i.properties[p]).min().unwrap_or(0);
+            if most < self.limits.floors[p] {
+                return false;
+            }
+            if Some(p) == self.limits.calorie_property {
+                if most < *self.limits.calories.start() || least > *self.limits.calories.end() {
+                    return false;
                 }
+            } else {
+                bounds.push((p, most));
             }
         }
+        let Some((best, _)) = self.best else {
+            return true;
+        };
+        if bounds.iter().any(|(_, most)| *most <= 0) {
+            // nothing but 0 from here
+            return false;
+        }
+        // by AM-GM with the properties scaled down by how much they could get at most, or by
+        // how much they got in the best recipe so far, which is spot on near the best recipe
+        let by_most = self
+            .am_gm_bound(rest, left, &bounds, |p| {
+                bounds
+                    .iter()
+                    .find(|(q, _)| *q == p)
+                    .map_or(1.0, |(_, most)| *most as f64)
+            })
+            .min(bounds.iter().map(|(_, most)| *most as f64).product());
+        let by_best = self
+            .best_totals
+            .as_ref()
+            .filter(|totals| bounds.iter().all(|(p, _)| totals[*p] > 0))
+            .map_or(f64::MAX, |totals| {
+                self.am_gm_bound(rest, left, &bounds, |p| totals[p] as f64)
+            });
+        by_most.min(by_best) * (1.0 + 1e-9) >= best.saturating_add(1) as f64
     }
-    best
+
+    /// The product of the properties in `bounds` is at most the product of the `scale`s
+    /// times the power of the most the scaled down properties can get on average.
+    fn am_gm_bound<F>(
+        &self,
+        rest: &[Ingredient],
+        left: isize,
+        bounds: &[(usize, isize)],
+        scale: F,
+    ) -> f64
+    where
+        F: Fn(usize) -> f64,
+    {
+        let scaled_now = bounds
+            .iter()
+            .map(|(p, _)| self.totals[*p] as f64 / scale(*p))
+            .sum::<f64>();
+        let scaled_rest = rest
+            .iter()
+            .map(|i| {
+                bounds
+                    .iter()
+                    .map(|(p, _)| i.properties[*p] as f64 / scale(*p))
+                    .sum::<f64>()
+            })
+            .fold(f64::MIN, f64::max);
+        let m = bounds.len() as f64;
+        let mean = (scaled_now + left as f64 * scaled_rest) / m;
+        bounds.iter().map(|(p, _)| scale(*p)).product::<f64>() * mean.max(0.0).powf(m)
+    }
+}
+
+fn print_recipe(recipe: &Recipe) {
+    let teaspoons = recipe
+        .teaspoons
+        .iter()
+        .map(|(name, amount)| format!("{amount} {name}"))
+        .collect::<Vec<_>>();
+    let totals = recipe
+        .totals
+        .iter()
+        .map(|(property, total)| format!("{property} {total}"))
+        .collect::<Vec<_>>();
+    println!("  {} ({})", teaspoons.join(", "), totals.join(", "));
 }
 
 fn main() {
-    println!("Part 1: {}", solve(None));
-    println!("Part 2: {}", solve(Some(500)));
+    let pantry = Pantry::parse(include_str!("../../inputs/day15.txt")).expect("input should parse");
+    let best = pantry
+        .best_recipe(Pantry::TEASPOONS, &[])
+        .expect("there should be a recipe");
+    println!("Part 1: {}", best.score);
+    print_recipe(&best);
+    let best = pantry
+        .best_recipe(Pantry::TEASPOONS, &[Constraint::Calories(500..=500)])
+        .expect("there should be a recipe");
+    println!("Part 2: {}", best.score);
+    print_recipe(&best);
+    if let Some(climbed) = pantry.climb_recipe(1000, &[], 10, 2015) {
+        println!("Hill climbing with 1000 teaspoons: {}", climbed.score);
+        print_recipe(&climbed);
+    }
+    let constraints = [
+        Constraint::Calories(300..=450),
+        Constraint::Teaspoons("Sugar", 0..=20),
+        Constraint::AtLeast("flavor", 150),
+    ];
+    match pantry.best_recipe(Pantry::TEASPOONS, &constraints) {
+        Some(best) => {
+            println!("Lighter: {}", best.score);
+            print_recipe(&best);
+        }
+        None => println!("Lighter: impossible"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2015::compositions::weak_compositions;
+
+    /// Tries every recipe.
+    fn brute_force_best_recipe<'a>(
+        pantry: &Pantry<'a>,
+        teaspoons: usize,
+        constraints: &[Constraint],
+    ) -> Option<Recipe<'a>> {
+        weak_compositions(teaspoons, pantry.ingredients.len())
+            .filter(|teaspoons| pantry.satisfies(teaspoons, constraints))
+            .max_by_key(|teaspoons| pantry.score(teaspoons))
+            .map(|teaspoons| pantry.recipe(&teaspoons))
+    }
+
+    const SAMPLE: &str = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
+
+    #[test]
+    fn test_sample() {
+        let pantry = Pantry::parse(SAMPLE).unwrap();
+        assert_eq!(
+            pantry.properties,
+            vec!["capacity", "durability", "flavor", "texture", "calories"]
+        );
+        assert_eq!(pantry.ingredients[1].name, "Cinnamon");
+        assert_eq!(pantry.score(&[44, 56]), 62842880);
+        assert_eq!(pantry.totals(&[40, 60])[4], 500);
+        assert_eq!(
+            pantry.best_recipe(Pantry::TEASPOONS, &[]),
+            Some(Recipe {
+                teaspoons: vec![("Butterscotch", 44), ("Cinnamon", 56)],
+                totals: vec![
+                    ("capacity", 68),
+                    ("durability", 80),
+                    ("flavor", 152),
+                    ("texture", 76),
+                    ("calories", 520),
+                ],
+                score: 62842880,
+            })
+        );
+        let best = pantry
+            .best_recipe(Pantry::TEASPOONS, &[Constraint::Calories(500..=500)])
+            .unwrap();
+        assert_eq!(best.teaspoons, vec![("Butterscotch", 40), ("Cinnamon", 60)]);
+        assert_eq!(best.score, 57600000);
+    }
+
+    #[test]
+    fn test_other_shapes() {
+        let pantry = Pantry::parse(
+            "Salt: taste 3, crunch -1
+Honey: taste -1, crunch 2
+Nuts: taste 0, crunch 1",
+        )
+        .unwrap();
+        assert_eq!(pantry.properties, vec!["taste", "crunch"]);
+        assert_eq!(pantry.score(&[34, 0, 66]), 102 * 32);
+        // taste 84, crunch 62 and no nuts at all
+        assert_eq!(
+            pantry.best_recipe(Pantry::TEASPOONS, &[]).unwrap().score,
+            5208
+        );
+        assert_eq!(
+            Pantry::parse("Salt: taste 3, crunch -1\nHoney: crunch 2, taste -1"),
+            Err(())
+        );
+        assert_eq!(Pantry::parse("Salt: taste three"), Err(()));
+
+        // more than a u64 can hold, then more than a u128 can
+        let pantry = Pantry::parse("A: a 9, b 9, c 9, d 9, e 9, f 9, g 9, h 9").unwrap();
+        let best = pantry.best_recipe(Pantry::TEASPOONS, &[]).unwrap();
+        assert_eq!(best.score, 900u128.pow(8));
+        let properties = (0..14).map(|p| format!("p{p} 9")).collect::<Vec<_>>();
+        let input = format!("A: {}", properties.join(", "));
+        let pantry = Pantry::parse(&input).unwrap();
+        assert_eq!(pantry.score(&[Pantry::TEASPOONS]), u128::MAX);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let pantry = Pantry::parse(SAMPLE).unwrap();
+        let brute_force = |constraints: &[Constraint]| {
+            (0..=100)
+                .map(|butterscotch| [butterscotch, 100 - butterscotch])
+                .filter(|teaspoons| pantry.satisfies(teaspoons, constraints))
+                .map(|teaspoons| pantry.score(&teaspoons))
+                .max()
+        };
+        for constraints in [
+            vec![Constraint::Calories(400..=510)],
+            vec![Constraint::Teaspoons("Butterscotch", 0..=30)],
+            vec![Constraint::Teaspoons("Cinnamon", 10..=20)],
+            vec![Constraint::AtLeast("durability", 100)],
+            vec![
+                Constraint::AtLeast("texture", 50),
+                Constraint::Calories(0..=600),
+            ],
+        ] {
+            let best = pantry.best_recipe(Pantry::TEASPOONS, &constraints);
+            assert_eq!(
+                best.as_ref().map(|recipe| recipe.score),
+                brute_force(&constraints),
+                "{constraints:?}"
+            );
+            let best = best.unwrap();
+            let teaspoons = best.teaspoons.iter().map(|t| t.1).collect::<Vec<_>>();
+            assert!(pantry.satisfies(&teaspoons, &constraints));
+        }
+        // can't have both
+        let constraints = [
+            Constraint::AtLeast("durability", 200),
+            Constraint::AtLeast("texture", 200),
+        ];
+        assert_eq!(pantry.best_recipe(Pantry::TEASPOONS, &constraints), None);
+        assert_eq!(
+            pantry.best_recipe(
+                Pantry::TEASPOONS,
+                &[Constraint::Teaspoons("Sugar", 0..=100)]
+            ),
+            None
+        );
+    }
+
+    /// Made up but repeatable ingredients, with calories.
+    fn generated_input(ingredients: usize, properties: usize, rng: &mut Rng) -> String {
+        let mut input = String::new();
+        for i in 0..ingredients {
+            let properties = (0..properties)
+                .map(|p| format!("p{p} {}", rng.below(11) as isize - 4))
+                .collect::<Vec<_>>();
+            input += &format!(
+                "I{i}: {}, calories {}\n",
+                properties.join(", "),
+                rng.below(9)
+            );
+        }
+        input
+    }
+
+    #[test]
+    fn test_search_against_brute_force() {
+        let mut rng = Rng::new(15);
+        for round in 0..60 {
+            let input = generated_input(2 + round % 3, 2 + round % 2, &mut rng);
+            let pantry = Pantry::parse(input.trim_end()).unwrap();
+            let teaspoons = 10 + rng.below(30);
+            let mut constraints = Vec::new();
+            if rng.below(2) == 0 {
+                let least = rng.below(4 * teaspoons) as isize;
+                constraints.push(Constraint::Calories(least..=least + 40));
+            }
+            if rng.below(2) == 0 {
+                let name = pantry.ingredients[rng.below(pantry.ingredients.len())].name;
+                constraints.push(Constraint::Teaspoons(name, 2..=teaspoons / 2));
+            }
+            if rng.below(3) == 0 {
+                constraints.push(Constraint::AtLeast("p0", rng.below(20) as isize));
+            }
+
+            let best = brute_force_best_recipe(&pantry, teaspoons, &constraints);
+            let best_score = best.as_ref().map(|recipe| recipe.score);
+            let found = pantry.best_recipe(teaspoons, &constraints);
+            assert_eq!(
+                found.as_ref().map(|recipe| recipe.score),
+                best_score,
+                "{input}{teaspoons} {constraints:?}"
+            );
+            let climbed = pantry.climb_recipe(teaspoons, &constraints, 5, round as u64);
+            assert!(climbed.as_ref().map(|recipe| recipe.score) <= best_score);
+            for recipe in found.iter().chain(&climbed) {
+                let amounts = recipe.teaspoons.iter().map(|t| t.1).collect::<Vec<_>>();
+                assert_eq!(amounts.iter().sum::<usize>(), teaspoons);
+                assert!(pantry.satisfies(&amounts, &constraints));
+            }
+        }
+    }
+
+    #[test]
+    fn test_climbing() {
+        let pantry = Pantry::parse(SAMPLE).unwrap();
+        let climbed = pantry.climb_recipe(100, &[], 3, 1).unwrap();
+        assert_eq!(climbed.score, 62842880);
+        let climbed = pantry
+            .climb_recipe(100, &[Constraint::Calories(500..=500)], 3, 1)
+            .unwrap();
+        assert_eq!(climbed.score, 57600000);
+
+        // plenty of ingredients and teaspoons
+        let mut rng = Rng::new(2015);
+        let input = generated_input(8, 4, &mut rng);
+        let pantry = Pantry::parse(input.trim_end()).unwrap();
+        let best = pantry.best_recipe(300, &[]).unwrap();
+        assert!(best.score > 0);
+        assert_eq!(pantry.climb_recipe(300, &[], 10, 1), Some(best));
+    }
 }
@@ -1,40 +1,206 @@
 use std::collections::HashMap;
 
+/// What a reading on the tape says about the real count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Rule {
+    Equal(usize),
+    Less(usize),
+    Greater(usize),
+    AtMost(usize),
+    AtLeast(usize),
+    /// Both ends included.
+    Range(usize, usize),
+    Unknown,
+}
+
+impl Rule {
+    fn matches(&self, count: usize) -> bool {
+        match *self {
+            Rule::Equal(value) => count == value,
+            Rule::Less(value) => count < value,
+            Rule::Greater(value) => count > value,
+            Rule::AtMost(value) => count <= value,
+            Rule::AtLeast(value) => count >= value,
+            Rule::Range(least, most) => (least..=most).contains(&count),
+            Rule::Unknown => true,
+        }
+    }
+}
+
 struct Matcher<'a> {
-    props: HashMap<&'a str, usize>,
+    rules: HashMap<&'a str, Rule>,
 }
 
 impl<'a> Matcher<'a> {
-    fn new(props: HashMap<&'a str, usize>) -> Self {
-        Matcher { props }
+    fn parse(s: &'a str) -> Result<Self, ()> {
+        // cats > 7
+        // perfumes in 1..=3
+        // dogs ?
+        let parse_usize = |s: &str| s.parse::<usize>().map_err(|_| ());
+        let mut rules = HashMap::new();
+        for line in s.lines() {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let (name, rule) = match parts.as_slice() {
+                [name, "?"] => (name, Rule::Unknown),
+                [name, "in", range] => {
+                    let (least, most) = range.split_once("..=").ok_or(())?;
+                    (name, Rule::Range(parse_usize(least)?, parse_usize(most)?))
+                }
+                [name, op, value] => {
+                    let value = parse_usize(value)?;
+                    let rule = match *op {
+                        "=" => Rule::Equal(value),
+                        "<" => Rule::Less(value),
+                        ">" => Rule::Greater(value),
+                        "<=" => Rule::AtMost(value),
+                        ">=" => Rule::AtLeast(value),
+                        _ => return Err(()),
+                    };
+                    (name, rule)
+                }
+                _ => return Err(()),
+            };
+            if rules.insert(*name, rule).is_some() {
+                return Err(());
+            }
+        }
+        Ok(Matcher { rules })
     }
 
+    /// Properties missing from the tape count as unknown.
     fn matches(&self, other: &HashMap<&str, usize>) -> bool {
-        for (k, v) in other {
-            if self.props.get(k).expect("matcher should have all fields") != v {
-                return false;
-            }
-        }
-        true
+        other
+            .iter()
+            .all(|(k, v)| self.rules.get(k).is_none_or(|rule| rule.matches(*v)))
+    }
+
+    /// How each of the properties compares to the tape, by name.
+    fn explain<'s>(&self, other: &HashMap<&'s str, usize>) -> Vec<Explanation<'s>> {
+        let mut explanations = other
+            .iter()
+            .map(|(name, count)| {
+                let outcome = match self.rules.get(name) {
+                    None | Some(Rule::Unknown) => Outcome::Unknown,
+                    Some(rule) if rule.matches(*count) => Outcome::Agrees(*rule),
+                    Some(rule) => Outcome::Contradicts(*rule),
+                };
+                Explanation {
+                    property: name,
+                    count: *count,
+                    outcome,
+                }
+            })
+            .collect::<Vec<_>>();
+        explanations.sort_by_key(|explanation| explanation.property);
+        explanations
+    }
+
+    /// The `top` best candidates by their weighted outcomes, best first.
+    fn rank<'s>(
+        &self,
+        sues: &[(usize, HashMap<&'s str, usize>)],
+        weights: &Weights,
+        top: usize,
+    ) -> Vec<Candidate<'s>> {
+        let mut candidates = sues
+            .iter()
+            .map(|(sue, props)| {
+                let explanations = self.explain(props);
+                Candidate {
+                    sue: *sue,
+                    score: explanations
+                        .iter()
+                        .map(|explanation| weights.of(&explanation.outcome))
+                        .sum(),
+                    explanations,
+                }
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|candidate| (-candidate.score, candidate.sue));
+        candidates.truncate(top);
+        candidates
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Agrees(Rule),
+    Contradicts(Rule),
+    /// Not on the tape, or on it as unknown.
+    Unknown,
+}
 
-    fn complex_comparison_direction(key: &str) -> std::cmp::Ordering {
-        match key {
-            "cats" | "trees" => std::cmp::Ordering::Greater,
-            "pomeranians" | "goldfish" => std::cmp::Ordering::Less,
-            _ => std::cmp::Ordering::Equal,
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Explanation<'a> {
+    property: &'a str,
+    count: usize,
+    outcome: Outcome,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Candidate<'a> {
+    sue: usize,
+    score: isize,
+    explanations: Vec<Explanation<'a>>,
+}
+
+/// How much each outcome counts for a candidate.
+#[derive(Clone, Copy, Debug)]
+struct Weights {
+    agrees: isize,
+    contradicts: isize,
+    unknown: isize,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            agrees: 1,
+            contradicts: -1,
+            unknown: 0,
         }
     }
+}
 
-    fn matches_complex(&self, other: &HashMap<&str, usize>) -> bool {
-        for (k, v) in other {
-            if v.cmp(self.props.get(k).expect("matcher should have all fields"))
-                != Self::complex_comparison_direction(k)
-            {
-                return false;
-            }
+impl Weights {
+    fn of(&self, outcome: &Outcome) -> isize {
+        match outcome {
+            Outcome::Agrees(_) => self.agrees,
+            Outcome::Contradicts(_) => self.contradicts,
+            Outcome::Unknown => self.unknown,
         }
-        true
+    }
+}
+
+fn print_candidates(candidates: &[Candidate]) {
+    let describe = |rule: &Rule| match *rule {
+        Rule::Equal(value) => format!("= {value}"),
+        Rule::Less(value) => format!("< {value}"),
+        Rule::Greater(value) => format!("> {value}"),
+        Rule::AtMost(value) => format!("<= {value}"),
+        Rule::AtLeast(value) => format!(">= {value}"),
+        Rule::Range(least, most) => format!("in {least}..={most}"),
+        Rule::Unknown => "?".to_string(),
+    };
+    for candidate in candidates {
+        let explanations = candidate
+            .explanations
+            .iter()
+            .map(|explanation| {
+                let outcome = match &explanation.outcome {
+                    Outcome::Agrees(rule) => format!("agrees with {}", describe(rule)),
+                    Outcome::Contradicts(rule) => format!("contradicts {}", describe(rule)),
+                    Outcome::Unknown => "unknown".to_string(),
+                };
+                format!("{} {} {outcome}", explanation.property, explanation.count)
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "  Sue {} ({:+}): {}",
+            candidate.sue,
+            candidate.score,
+            explanations.join(", ")
+        );
     }
 }
 
@@ -65,35 +231,107 @@ fn parse(s: &str) -> Result<(usize, HashMap<&str, usize>), ()> {
 }
 
 fn main() {
-    let matcher = Matcher::new(
-        [
-            ("children", 3),
-            ("cats", 7),
-            ("samoyeds", 2),
-            ("pomeranians", 3),
-            ("akitas", 0),
-            ("vizslas", 0),
-            ("goldfish", 5),
-            ("trees", 3),
-            ("cars", 2),
-            ("perfumes", 1),
-        ]
-        .into_iter()
-        .collect(),
-    );
     let sues: Vec<_> = include_str!("../../inputs/day16.txt")
         .lines()
         .map(parse)
         .collect::<Result<_, _>>()
         .expect("input should parse");
-    for (sue_idx, sue_props) in &sues {
-        if matcher.matches(sue_props) {
-            println!("Part 1: {sue_idx}");
+    for (part, tape) in [
+        (1, include_str!("../../inputs/day16_tape.txt")),
+        (2, include_str!("../../inputs/day16_tape_calibrated.txt")),
+    ] {
+        let matcher = Matcher::parse(tape).expect("tape should parse");
+        for (sue_idx, sue_props) in &sues {
+            if matcher.matches(sue_props) {
+                println!("Part {part}: {sue_idx}");
+            }
         }
+        print_candidates(&matcher.rank(&sues, &Weights::default(), 3));
     }
-    for (sue_idx, sue_props) in &sues {
-        if matcher.matches_complex(sue_props) {
-            println!("Part 2: {sue_idx}");
-        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let matcher = Matcher::parse(
+            "cats > 7
+trees >= 3
+goldfish < 5
+cars <= 2
+perfumes in 1..=3
+akitas = 0
+dogs ?",
+        )
+        .unwrap();
+        assert_eq!(matcher.rules["perfumes"], Rule::Range(1, 3));
+        let sue = |s| parse(s).unwrap().1;
+        assert!(matcher.matches(&sue("Sue 1: cats: 8, trees: 3, dogs: 100")));
+        assert!(matcher.matches(&sue("Sue 2: goldfish: 4, cars: 2, perfumes: 3")));
+        assert!(!matcher.matches(&sue("Sue 3: cats: 7")));
+        assert!(!matcher.matches(&sue("Sue 4: perfumes: 4")));
+        assert!(!matcher.matches(&sue("Sue 5: goldfish: 5")));
+        assert!(!matcher.matches(&sue("Sue 6: akitas: 1, dogs: 0")));
+
+        assert!(Matcher::parse("cats ~ 7").is_err());
+        assert!(Matcher::parse("cats in 1..3").is_err());
+        assert!(Matcher::parse("cats = 1\ncats = 2").is_err());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let matcher = Matcher::parse("cats > 7\ntrees = 3\ndogs ?").unwrap();
+        let sues = [
+            "Sue 1: cats: 8, trees: 3, dogs: 1",
+            "Sue 2: cats: 7, trees: 3, birds: 2",
+            "Sue 3: cats: 1, trees: 1, dogs: 2",
+            "Sue 4: cats: 9, trees: 4, dogs: 3",
+        ]
+        .into_iter()
+        .map(parse)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        // a property the tape doesn't have is no reason to panic
+        assert!(!matcher.matches(&sues[1].1));
+
+        let ranked = matcher.rank(&sues, &Weights::default(), 3);
+        assert_eq!(
+            ranked.iter().map(|c| (c.sue, c.score)).collect::<Vec<_>>(),
+            vec![(1, 2), (2, 0), (4, 0)]
+        );
+        assert_eq!(
+            ranked[1].explanations,
+            vec![
+                Explanation {
+                    property: "birds",
+                    count: 2,
+                    outcome: Outcome::Unknown,
+                },
+                Explanation {
+                    property: "cats",
+                    count: 7,
+                    outcome: Outcome::Contradicts(Rule::Greater(7)),
+                },
+                Explanation {
+                    property: "trees",
+                    count: 3,
+                    outcome: Outcome::Agrees(Rule::Equal(3)),
+                },
+            ]
+        );
+
+        // being wrong hurts a lot more than being unknown
+        let weights = Weights {
+            agrees: 1,
+            contradicts: -10,
+            unknown: -1,
+        };
+        let ranked = matcher.rank(&sues, &weights, 10);
+        assert_eq!(
+            ranked.iter().map(|c| (c.sue, c.score)).collect::<Vec<_>>(),
+            vec![(1, 1), (2, -10), (4, -10), (3, -21)]
+        );
     }
 }
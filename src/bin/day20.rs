@@ -6,7 +6,7 @@ fn divisors_sum(n: usize) -> usize {
     let mut sum = 0;
     let mut i = 1;
     while i * i <= n {
-        if n % i == 0 {
+        if n.is_multiple_of(i) {
             sum += i;
             if n / i != i {
                 sum += n / i;
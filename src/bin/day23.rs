@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+#[allow(dead_code)] // payloads are only read through `Debug`
 #[derive(Clone, Debug)]
 enum Error {
     UnexpectedEndOfString,
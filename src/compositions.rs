@@ -0,0 +1,85 @@
+//! Weak compositions: all the ways to split a number into a fixed number of parts, some of
+//! which may be zero.
+
+/// All the weak compositions of `total` into `parts` parts, in lexicographic order from
+/// `[0, .., 0, total]` to `[total, 0, .., 0]`.
+///
+/// By stars and bars there are `C(total + parts - 1, parts - 1)` of them.
+pub struct WeakCompositions {
+    next: Option<Vec<usize>>,
+}
+
+pub fn weak_compositions(total: usize, parts: usize) -> WeakCompositions {
+    let next = match parts {
+        0 if total > 0 => None,
+        0 => Some(Vec::new()),
+        _ => {
+            let mut first = vec![0; parts];
+            first[parts - 1] = total;
+            Some(first)
+        }
+    };
+    WeakCompositions { next }
+}
+
+impl Iterator for WeakCompositions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        // move one star left over the last bar with stars behind it, and put all the other
+        // stars that were behind it at the very end
+        if let Some(last) = (1..current.len()).rev().find(|idx| current[*idx] > 0) {
+            let mut next = current.clone();
+            let rest = next[last] - 1;
+            next[last - 1] += 1;
+            next[last] = 0;
+            let end = next.len() - 1;
+            next[end] = rest;
+            self.next = Some(next);
+        }
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small() {
+        assert_eq!(
+            weak_compositions(2, 3).collect::<Vec<_>>(),
+            vec![
+                vec![0, 0, 2],
+                vec![0, 1, 1],
+                vec![0, 2, 0],
+                vec![1, 0, 1],
+                vec![1, 1, 0],
+                vec![2, 0, 0],
+            ]
+        );
+        assert_eq!(weak_compositions(5, 1).collect::<Vec<_>>(), vec![vec![5]]);
+        assert_eq!(
+            weak_compositions(0, 2).collect::<Vec<_>>(),
+            vec![vec![0, 0]]
+        );
+        assert_eq!(weak_compositions(0, 0).collect::<Vec<_>>(), vec![vec![]]);
+        assert_eq!(weak_compositions(3, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_stars_and_bars() {
+        let binomial = |n: usize, k: usize| (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1));
+        for total in 0..8 {
+            for parts in 1..5 {
+                let all = weak_compositions(total, parts).collect::<Vec<_>>();
+                assert_eq!(all.len(), binomial(total + parts - 1, parts - 1));
+                assert!(all.iter().all(|c| c.iter().sum::<usize>() == total));
+                assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+            }
+        }
+        // 4 ingredients and 100 teaspoons
+        assert_eq!(weak_compositions(100, 4).count(), 176851);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod compositions;
+pub mod rle;
+pub mod rng;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,268 @@
+//! Run-length encoding: grouping runs of equal items and expanding them back.
+
+/// Groups consecutive equal items into `(count, item)` pairs.
+pub struct Uniq<I, T> {
+    iter: I,
+    next: Option<T>,
+}
+
+impl<I, T> Iterator for Uniq<I, T>
+where
+    I: Iterator<Item = T>,
+    T: PartialEq,
+{
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        let mut count = 1;
+        loop {
+            match self.iter.next() {
+                Some(item) if item == current => count += 1,
+                other => {
+                    self.next = other;
+                    return Some((count, current));
+                }
+            }
+        }
+    }
+}
+
+pub trait AddUniq<I, T> {
+    fn uniq(self) -> Uniq<I, T>;
+}
+
+impl<I, T> AddUniq<I, T> for I
+where
+    I: Iterator<Item = T>,
+{
+    fn uniq(mut self) -> Uniq<I, T> {
+        let next = self.next();
+        Uniq { iter: self, next }
+    }
+}
+
+/// Groups consecutive items with equal keys into `(key, items)` pairs.
+pub struct Runs<I, F, T> {
+    iter: I,
+    key: F,
+    next: Option<T>,
+}
+
+impl<I, F, T, K> Iterator for Runs<I, F, T>
+where
+    I: Iterator<Item = T>,
+    F: FnMut(&T) -> K,
+    K: PartialEq,
+{
+    type Item = (K, Vec<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.next.take()?;
+        let key = (self.key)(&first);
+        let mut run = vec![first];
+        loop {
+            match self.iter.next() {
+                Some(item) if (self.key)(&item) == key => run.push(item),
+                other => {
+                    self.next = other;
+                    return Some((key, run));
+                }
+            }
+        }
+    }
+}
+
+pub trait AddRuns<I, F, T> {
+    fn runs(self, key: F) -> Runs<I, F, T>;
+}
+
+impl<I, F, T, K> AddRuns<I, F, T> for I
+where
+    I: Iterator<Item = T>,
+    F: FnMut(&T) -> K,
+{
+    fn runs(mut self, key: F) -> Runs<I, F, T> {
+        let next = self.next();
+        Runs {
+            iter: self,
+            key,
+            next,
+        }
+    }
+}
+
+/// Expands `(count, item)` pairs back into `count` copies of `item`.
+pub struct Decode<I, T> {
+    iter: I,
+    current: Option<(usize, T)>,
+}
+
+impl<I, T> Iterator for Decode<I, T>
+where
+    I: Iterator<Item = (usize, T)>,
+    T: Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((count, item)) = self.current.as_mut() {
+                if *count > 0 {
+                    *count -= 1;
+                    return Some(item.clone());
+                }
+            }
+            self.current = Some(self.iter.next()?);
+        }
+    }
+}
+
+pub trait AddDecode<I, T> {
+    fn decode(self) -> Decode<I, T>;
+}
+
+impl<I, T> AddDecode<I, T> for I
+where
+    I: Iterator<Item = (usize, T)>,
+{
+    fn decode(self) -> Decode<I, T> {
+        Decode {
+            iter: self,
+            current: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Digits can't be encoded as they would be read back as counts.
+    DigitInInput(u8),
+    CountWithoutByte,
+    CountTooLarge,
+}
+
+/// Encodes bytes as runs like `3a2bc` (a count of 1 is left out).
+///
+/// Fails on ASCII digits as those couldn't be told apart from counts.
+pub fn encode_bytes(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    for (count, byte) in bytes.iter().cloned().uniq() {
+        if byte.is_ascii_digit() {
+            return Err(Error::DigitInInput(byte));
+        }
+        if count > 1 {
+            result.extend(count.to_string().bytes());
+        }
+        result.push(byte);
+    }
+    Ok(result)
+}
+
+/// Reverses `encode_bytes`.
+pub fn decode_bytes(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    let mut count: Option<usize> = None;
+    for byte in encoded {
+        if byte.is_ascii_digit() {
+            let digit = usize::from(byte - b'0');
+            count = Some(
+                count
+                    .unwrap_or_default()
+                    .checked_mul(10)
+                    .and_then(|c| c.checked_add(digit))
+                    .ok_or(Error::CountTooLarge)?,
+            );
+        } else {
+            result.extend(std::iter::repeat_n(*byte, count.take().unwrap_or(1)));
+        }
+    }
+    if count.is_some() {
+        Err(Error::CountWithoutByte)
+    } else {
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniq_empty() {
+        let v: Vec<usize> = Vec::new();
+        let mut iter = v.into_iter().uniq();
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_uniq() {
+        let v = vec![1, 1, 2, 3, 3, 3, 1, 1];
+        let mut iter = v.into_iter().uniq();
+        assert_eq!(iter.next(), Some((2, 1)));
+        assert_eq!(iter.next(), Some((1, 2)));
+        assert_eq!(iter.next(), Some((3, 3)));
+        assert_eq!(iter.next(), Some((2, 1)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_runs() {
+        let words = [
+            "apple",
+            "avocado",
+            "banana",
+            "blueberry",
+            "cherry",
+            "apricot",
+        ];
+        let runs = words
+            .into_iter()
+            .runs(|w| w.chars().next())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            runs,
+            vec![
+                (Some('a'), vec!["apple", "avocado"]),
+                (Some('b'), vec!["banana", "blueberry"]),
+                (Some('c'), vec!["cherry"]),
+                (Some('a'), vec!["apricot"]),
+            ]
+        );
+        assert_eq!(std::iter::empty::<usize>().runs(|x| *x).next(), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        let v = vec![1, 1, 2, 3, 3, 3, 1, 1];
+        assert_eq!(v.iter().cloned().uniq().decode().collect::<Vec<_>>(), v);
+        assert_eq!(
+            vec![(0, 'a'), (2, 'b'), (0, 'c'), (1, 'd')]
+                .into_iter()
+                .decode()
+                .collect::<String>(),
+            "bbd"
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(encode_bytes(b"aaabbc"), Ok(b"3a2bc".to_vec()));
+        assert_eq!(encode_bytes(b""), Ok(Vec::new()));
+        assert_eq!(encode_bytes(b"a1"), Err(Error::DigitInInput(b'1')));
+        assert_eq!(decode_bytes(b"3a2bc"), Ok(b"aaabbc".to_vec()));
+        assert_eq!(decode_bytes(b"12#."), Ok(b"############.".to_vec()));
+        assert_eq!(decode_bytes(b"a3"), Err(Error::CountWithoutByte));
+        assert_eq!(
+            decode_bytes(b"99999999999999999999999a"),
+            Err(Error::CountTooLarge)
+        );
+
+        let pattern = b".#.#.#\n...##.\n#....#\n..#...\n#.#..#\n####..";
+        assert_eq!(
+            decode_bytes(&encode_bytes(pattern).unwrap()),
+            Ok(pattern.to_vec())
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! A small SplitMix64 generator, so randomised searches and tests are reproducible from a
+//! seed.
+
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, which must not be empty.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let (mut rng1, mut rng2) = (Rng::new(2015), Rng::new(2015));
+        let draws = (0..100).map(|_| rng1.below(6)).collect::<Vec<_>>();
+        assert_eq!(draws, (0..100).map(|_| rng2.below(6)).collect::<Vec<_>>());
+        assert!(draws.iter().all(|d| *d < 6));
+        assert!((0..6).all(|d| draws.contains(&d)));
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+}